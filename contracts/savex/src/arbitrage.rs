@@ -1,87 +1,458 @@
 //! Arbitrage Module for SaveX
 //!
 //! Automated arbitrage execution between different DEXs on Stellar
-
-use soroban_sdk::{contract, contractimpl, token, Address, Env, Vec};
+//!
+//! `ArbitrageBot` is deployed as its own contract with its own admin-gated
+//! configuration. It shares the crate (and so the wasm exports) with
+//! `SaveXContract`, which is why its config functions carry an `arb_` prefix.
+//! Every SaveX deployment therefore also exposes the bot's functions, so an
+//! instance can only ever be set up as one of the two: `arb_initialize` refuses
+//! an initialized SaveX and `initialize` refuses a configured bot.
+
+use soroban_sdk::{
+    auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
+    contract, contractimpl, token, vec,
+    xdr::FromXdr,
+    Address, Bytes, Env, IntoVal, Symbol, Vec, U256,
+};
+use crate::events;
 use crate::types::*;
-use crate::SoroswapRouterClient;
+use crate::{
+    SoroswapFactoryClient, SoroswapPairClient, SoroswapRouterClient, INSTANCE_BUMP_AMOUNT,
+    INSTANCE_LIFETIME_THRESHOLD,
+};
+
+/// Most candidate cycles `best_cycle` will quote in one call
+const MAX_CANDIDATE_CYCLES: u32 = 10;
+
+/// Soroswap pairs keep 997/1000 of every input (0.3% fee)
+const SOROSWAP_FEE_NUMERATOR: u32 = 997;
+const SOROSWAP_FEE_DENOMINATOR: u32 = 1000;
+
+/// Load the bot configuration
+fn load_config(env: &Env) -> Result<ArbitrageConfig, SaveXError> {
+    env.storage()
+        .instance()
+        .get(&ArbitrageDataKey::Config)
+        .ok_or(SaveXError::NotInitialized)
+}
+
+/// Helper: Store the bot configuration and publish `ArbitrageConfigUpdated`
+fn store_config(env: &Env, config: &ArbitrageConfig) {
+    env.storage().instance().set(&ArbitrageDataKey::Config, config);
+    env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+
+    events::ArbitrageConfigUpdated {
+        admin: config.admin.clone(),
+        config: config.clone(),
+    }
+    .publish(env);
+}
+
+/// Load the configuration and check `admin` is its admin
+fn require_admin(env: &Env, admin: &Address) -> Result<ArbitrageConfig, SaveXError> {
+    admin.require_auth();
+
+    let config = load_config(env)?;
+    if config.admin != *admin {
+        return Err(SaveXError::NotAuthorized);
+    }
+    Ok(config)
+}
+
+/// Get the router address configured for arbitrage execution
+fn router_address(env: &Env) -> Result<Address, SaveXError> {
+    load_config(env)
+        .map(|config| config.router)
+        .map_err(|_| SaveXError::RouterNotConfigured)
+}
+
+/// Check the bot is running and `executor` may trade; returns the configuration
+fn require_executor(env: &Env, executor: &Address) -> Result<ArbitrageConfig, SaveXError> {
+    executor.require_auth();
+
+    let config = load_config(env)?;
+
+    if config.is_paused {
+        return Err(SaveXError::ContractPaused);
+    }
+
+    let allowed: bool = env.storage()
+        .persistent()
+        .get(&ArbitrageDataKey::Executor(executor.clone()))
+        .unwrap_or(false);
+    if !allowed {
+        return Err(SaveXError::NotAuthorized);
+    }
+
+    Ok(config)
+}
+
+/// Whether `path` has at least one intermediate token and returns to its start
+fn is_cycle(path: &Vec<Address>) -> bool {
+    path.len() >= 3 && path.get(0).unwrap() == path.get(path.len() - 1).unwrap()
+}
+
+/// Quote `amount` round the cycle `path` on the router; a route the router can't
+/// quote (e.g. a missing pair) is an `InvalidPath`
+fn quote_cycle(
+    router: &SoroswapRouterClient,
+    path: &Vec<Address>,
+    amount: i128,
+) -> Result<CycleSimulation, SaveXError> {
+    if !is_cycle(path) {
+        return Err(SaveXError::InvalidPath);
+    }
+
+    let amounts = match router.try_get_amounts_out(&amount, path) {
+        Ok(Ok(amounts)) => amounts,
+        _ => return Err(SaveXError::InvalidPath),
+    };
+    let amount_out = amounts.get(amounts.len() - 1).unwrap();
+
+    Ok(CycleSimulation {
+        path: path.clone(),
+        amount_in: amount,
+        amount_out,
+        profit: amount_out - amount,
+    })
+}
+
+/// Reserves of the `token_in`/`token_out` pair, as (reserve_in, reserve_out)
+fn hop_reserves(
+    env: &Env,
+    factory: &SoroswapFactoryClient,
+    token_in: &Address,
+    token_out: &Address,
+) -> (i128, i128) {
+    let pair = SoroswapPairClient::new(env, &factory.get_pair(token_in, token_out));
+    let (reserve_0, reserve_1, _) = pair.get_reserves();
+    if pair.token_0() == *token_in {
+        (reserve_0, reserve_1)
+    } else {
+        (reserve_1, reserve_0)
+    }
+}
+
+/// `value` with the swap fee taken off
+fn after_fee(env: &Env, value: &U256) -> U256 {
+    value
+        .mul(&U256::from_u32(env, SOROSWAP_FEE_NUMERATOR))
+        .div(&U256::from_u32(env, SOROSWAP_FEE_DENOMINATOR))
+}
+
+/// Integer square root (floor) by Newton's method, starting above the root
+fn isqrt(env: &Env, n: &U256) -> U256 {
+    let zero = U256::from_u32(env, 0);
+    if *n == zero {
+        return zero;
+    }
+
+    // 2^ceil(bits / 2) is at least the root
+    let bytes = n.to_be_bytes();
+    let mut bits = 0;
+    for (i, byte) in bytes.iter().enumerate() {
+        if byte != 0 {
+            bits = (bytes.len() - i as u32) * 8 - byte.leading_zeros();
+            break;
+        }
+    }
+    let mut x = U256::from_u32(env, 1).shl(bits.div_ceil(2));
+
+    loop {
+        let y = x.add(&n.div(&x)).shr(1);
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
+
+/// Flash-loan profit of `token` held by the bot
+fn retained(env: &Env, token: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&ArbitrageDataKey::Retained(token.clone()))
+        .unwrap_or(0)
+}
+
+/// Helper: Store the retained profit of `token`
+fn store_retained(env: &Env, token: &Address, amount: i128) {
+    env.storage().instance().set(&ArbitrageDataKey::Retained(token.clone()), &amount);
+    env.storage().instance().extend_ttl(INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+/// The venue configured on the bot
+fn default_venue(config: &ArbitrageConfig) -> ArbitrageVenue {
+    ArbitrageVenue {
+        router: config.router.clone(),
+        factory: config.factory.clone(),
+    }
+}
+
+/// Swap `amount` along `path` on `venue`, output to this contract
+/// The router moves the input from this contract into the first pair, which is a
+/// nested call and so needs explicit authorization from the contract
+/// Returns the output this contract actually received: venues are chosen by the
+/// executor, so the router's own figures can't be trusted with the bot's funds
+fn swap(
+    env: &Env,
+    venue: &ArbitrageVenue,
+    path: &Vec<Address>,
+    amount: i128,
+    min_output: i128,
+) -> Result<i128, SaveXError> {
+    let token_in = path.get(0).unwrap();
+    let token_out = path.get(path.len() - 1).unwrap();
+    let token_out_client = token::Client::new(env, &token_out);
+    let balance_before = token_out_client.balance(&env.current_contract_address());
+
+    let pair = SoroswapFactoryClient::new(env, &venue.factory).get_pair(&token_in, &path.get(1).unwrap());
+    env.authorize_as_current_contract(vec![
+        env,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: token_in.clone(),
+                fn_name: Symbol::new(env, "transfer"),
+                args: (env.current_contract_address(), pair, amount).into_val(env),
+            },
+            sub_invocations: Vec::new(env),
+        }),
+    ]);
+
+    // 5 min deadline
+    let deadline = env.ledger().timestamp() + 300;
+    SoroswapRouterClient::new(env, &venue.router).swap_exact_tokens_for_tokens(
+        &amount,
+        &min_output,
+        path,
+        &env.current_contract_address(),
+        &deadline,
+    );
+
+    let mut received = token_out_client.balance(&env.current_contract_address()) - balance_before;
+    if token_in == token_out {
+        // Round a cycle the input left the same balance
+        received += amount;
+    }
+    if received < min_output {
+        return Err(SaveXError::SlippageExceeded);
+    }
+    Ok(received)
+}
 
 #[contract]
 pub struct ArbitrageBot;
 
 #[contractimpl]
 impl ArbitrageBot {
-    
-    /// Execute a simple arbitrage trade between two DEXs
-    /// Buys from cheaper pool, sells to expensive pool
-    /// Returns profit amount
+
+    // ========================================================================
+    // Configuration
+    // ========================================================================
+
+    /// Initialize the bot with its admin and Soroswap addresses
+    /// Refused on an instance already initialized as SaveX, whose funds the bot
+    /// would otherwise be able to trade
+    pub fn arb_initialize(env: Env, admin: Address, router: Address, factory: Address) -> Result<(), SaveXError> {
+        if env.storage().instance().has(&ArbitrageDataKey::Config)
+            || env.storage().instance().has(&DataKey::Admin)
+        {
+            return Err(SaveXError::AlreadyInitialized);
+        }
+
+        admin.require_auth();
+
+        store_config(&env, &ArbitrageConfig {
+            admin,
+            router,
+            factory,
+            flash_lender: None,
+            is_paused: false,
+        });
+
+        Ok(())
+    }
+
+    /// Set the Soroswap router used for swaps (admin only)
+    pub fn arb_set_router(env: Env, admin: Address, router: Address) -> Result<(), SaveXError> {
+        let mut config = require_admin(&env, &admin)?;
+        config.router = router;
+        store_config(&env, &config);
+        Ok(())
+    }
+
+    /// Set the Soroswap factory used to look up pairs (admin only)
+    pub fn arb_set_factory(env: Env, admin: Address, factory: Address) -> Result<(), SaveXError> {
+        let mut config = require_admin(&env, &admin)?;
+        config.factory = factory;
+        store_config(&env, &config);
+        Ok(())
+    }
+
+    /// Set the flash lender allowed to call `on_flash_loan` (admin only)
+    pub fn arb_set_flash_lender(env: Env, admin: Address, lender: Address) -> Result<(), SaveXError> {
+        let mut config = require_admin(&env, &admin)?;
+        config.flash_lender = Some(lender);
+        store_config(&env, &config);
+        Ok(())
+    }
+
+    /// Allow or disallow an address to run trades (admin only)
+    pub fn arb_set_executor(env: Env, admin: Address, executor: Address, allowed: bool) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+
+        let key = ArbitrageDataKey::Executor(executor.clone());
+        if allowed {
+            env.storage().persistent().set(&key, &true);
+            env.storage().persistent().extend_ttl(&key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+        } else {
+            env.storage().persistent().remove(&key);
+        }
+
+        events::ArbitrageExecutorUpdated { admin, executor, allowed }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Stop all trades (admin only)
+    pub fn arb_pause(env: Env, admin: Address) -> Result<(), SaveXError> {
+        let mut config = require_admin(&env, &admin)?;
+        config.is_paused = true;
+        store_config(&env, &config);
+        Ok(())
+    }
+
+    /// Resume trading (admin only)
+    pub fn arb_unpause(env: Env, admin: Address) -> Result<(), SaveXError> {
+        let mut config = require_admin(&env, &admin)?;
+        config.is_paused = false;
+        store_config(&env, &config);
+        Ok(())
+    }
+
+    /// Pay retained flash-loan profit out of the bot (admin only)
+    pub fn arb_withdraw_profit(
+        env: Env,
+        admin: Address,
+        token: Address,
+        to: Address,
+        amount: i128,
+    ) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+
+        let held = retained(&env, &token);
+        if amount <= 0 {
+            return Err(SaveXError::InvalidAmount);
+        }
+        if amount > held {
+            return Err(SaveXError::InsufficientBalance);
+        }
+
+        store_retained(&env, &token, held - amount);
+        token::Client::new(&env, &token).transfer(&env.current_contract_address(), &to, &amount);
+
+        events::ArbitrageProfitWithdrawn { admin, token, to, amount }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Get the flash-loan profit of `token` held by the bot
+    pub fn arb_get_retained(env: Env, token: Address) -> i128 {
+        retained(&env, &token)
+    }
+
+    /// Get the bot configuration
+    pub fn arb_get_config(env: Env) -> Result<ArbitrageConfig, SaveXError> {
+        load_config(&env)
+    }
+
+    /// Check whether an address may run trades
+    pub fn arb_is_executor(env: Env, executor: Address) -> bool {
+        env.storage()
+            .persistent()
+            .get(&ArbitrageDataKey::Executor(executor))
+            .unwrap_or(false)
+    }
+
+    // ========================================================================
+    // Trading
+    // ========================================================================
+
+    /// Execute a round-trip arbitrage between two DEXs
+    /// Buys `token_b` with `token_a` on `route.buy`, sells it back on `route.sell`
+    /// Reverts unless the trade returns at least `amount + min_profit` of `token_a`
+    /// Each leg is settled on the balances the bot actually received, not on the
+    /// amounts the executor-chosen routers report
     pub fn execute_arbitrage(
         env: Env,
         executor: Address,
         token_a: Address,
         token_b: Address,
         amount: i128,
+        route: ArbitrageRoute,
         min_profit: i128,
-    ) -> i128 {
-        executor.require_auth();
+    ) -> Result<ArbitrageResult, SaveXError> {
+        require_executor(&env, &executor)?;
 
         if amount <= 0 {
-            panic!("Invalid amount");
+            return Err(SaveXError::InvalidAmount);
         }
 
         if min_profit < 0 {
-            panic!("Invalid minimum profit");
+            return Err(SaveXError::InvalidAmount);
+        }
+
+        if token_a == token_b {
+            return Err(SaveXError::InvalidPath);
         }
 
         // Transfer initial tokens from executor to contract
         token::Client::new(&env, &token_a).transfer(
             &executor,
-            &env.current_contract_address(),
+            env.current_contract_address(),
             &amount
         );
 
-        // Execute swap
-        let router_address: Address = env
-            .storage()
-            .instance()
-            .get(&DataKey::RouterAddress)
-            .expect("Router not configured");
-
-        let router = SoroswapRouterClient::new(&env, &router_address);
-
-        // Build swap path
-        let mut path = Vec::new(&env);
-        path.push_back(token_a.clone());
-        path.push_back(token_b.clone());
-
-        // Execute swap with 5 min deadline
-        let deadline = env.ledger().timestamp() + 300;
-        
-        let amounts = router.swap_exact_tokens_for_tokens(
-            &amount,
-            &min_profit,
-            &path,
-            &env.current_contract_address(),
-            &deadline,
-        );
+        // Buy on the first venue
+        let buy_path = vec![&env, token_a.clone(), token_b.clone()];
+        let amount_b = swap(&env, &route.buy, &buy_path, amount, 0)?;
 
-        let output = amounts.get(amounts.len() - 1).unwrap();
+        // Sell back on the second; the router enforces the minimum return
+        let sell_path = vec![&env, token_b.clone(), token_a.clone()];
+        let output = swap(&env, &route.sell, &sell_path, amount_b, amount + min_profit)?;
 
-        // Calculate profit
+        // Profit is in the starting token
         let profit = output - amount;
 
         if profit < min_profit {
-            panic!("Profit too low");
+            return Err(SaveXError::ProfitTooLow);
         }
 
-        // Transfer output tokens back to executor
-        token::Client::new(&env, &token_b).transfer(
+        // Return principal and profit to executor
+        token::Client::new(&env, &token_a).transfer(
             &env.current_contract_address(),
             &executor,
             &output
         );
 
-        profit
+        let path = vec![&env, token_a.clone(), token_b, token_a];
+        events::ArbitrageExecuted {
+            executor,
+            path: path.clone(),
+            amount_in: amount,
+            amount_out: output,
+            profit,
+        }
+        .publish(&env);
+
+        Ok(ArbitrageResult {
+            input_amount: amount,
+            output_amount: output,
+            profit,
+            path_used: path,
+            executed_at: env.ledger().timestamp(),
+        })
     }
 
     /// Execute triangular arbitrage through multiple pools
@@ -93,57 +464,33 @@ impl ArbitrageBot {
         path: Vec<Address>,  // Must form a cycle (start = end token)
         amount: i128,
         min_profit: i128,
-    ) -> i128 {
-        executor.require_auth();
-
+    ) -> Result<i128, SaveXError> {
         if amount <= 0 {
-            panic!("Invalid amount");
+            return Err(SaveXError::InvalidAmount);
         }
 
-        if path.len() < 3 {
-            panic!("Invalid path (need at least 3 tokens)");
+        if !is_cycle(&path) {
+            return Err(SaveXError::InvalidPath);
         }
 
         let start_token = path.get(0).unwrap();
-        let end_token = path.get(path.len() - 1).unwrap();
-
-        if start_token != end_token {
-            panic!("Path must form a cycle");
-        }
+        let config = require_executor(&env, &executor)?;
 
         // Transfer initial tokens from executor
         token::Client::new(&env, &start_token).transfer(
             &executor,
-            &env.current_contract_address(),
+            env.current_contract_address(),
             &amount
         );
 
-        let router_address: Address = env
-            .storage()
-            .instance()
-            .get(&DataKey::RouterAddress)
-            .expect("Router not configured");
-
-        let router = SoroswapRouterClient::new(&env, &router_address);
-
-        // Build full path for multi-hop swap
-        let deadline = env.ledger().timestamp() + 300;
-
-        let amounts = router.swap_exact_tokens_for_tokens(
-            &amount,
-            &0,  // No minimum for intermediate swaps
-            &path,
-            &env.current_contract_address(),
-            &deadline,
-        );
-
-        let final_output = amounts.get(amounts.len() - 1).unwrap();
+        // No minimum for intermediate swaps
+        let final_output = swap(&env, &default_venue(&config), &path, amount, 0)?;
 
         // Calculate profit
         let profit = final_output - amount;
 
         if profit < min_profit {
-            panic!("Profit too low");
+            return Err(SaveXError::ProfitTooLow);
         }
 
         // Return all tokens to executor
@@ -153,36 +500,137 @@ impl ArbitrageBot {
             &final_output
         );
 
-        profit
+        events::ArbitrageExecuted {
+            executor,
+            path,
+            amount_in: amount,
+            amount_out: final_output,
+            profit,
+        }
+        .publish(&env);
+
+        Ok(profit)
     }
 
     /// Estimate arbitrage profit without executing
-    /// Returns expected profit amount
+    /// Quotes the round trip `token_a -> token_b -> token_a`
+    /// Returns expected profit in `token_a`
     pub fn estimate_arbitrage_profit(
         env: Env,
         token_a: Address,
         token_b: Address,
         amount: i128,
-    ) -> i128 {
-        let router_address: Address = env
-            .storage()
-            .instance()
-            .get(&DataKey::RouterAddress)
-            .expect("Router not configured");
-
-        let router = SoroswapRouterClient::new(&env, &router_address);
-
-        // Build path
-        let mut path = Vec::new(&env);
-        path.push_back(token_a);
-        path.push_back(token_b);
-
-        // Get expected output
-        let amounts = router.get_amounts_out(&amount, &path);
-        let output = amounts.get(amounts.len() - 1).unwrap();
-
-        // Return profit
-        output - amount
+    ) -> Result<i128, SaveXError> {
+        let path = vec![&env, token_a.clone(), token_b, token_a];
+        Self::simulate_cycle(env, path, amount).map(|simulation| simulation.profit)
+    }
+
+    /// Quote `amount` round the cycle `path` (start token = end token) without executing
+    /// Amounts come from the router's `get_amounts_out`, so router fees are already taken
+    pub fn simulate_cycle(env: Env, path: Vec<Address>, amount: i128) -> Result<CycleSimulation, SaveXError> {
+        let router = SoroswapRouterClient::new(&env, &router_address(&env)?);
+
+        if amount <= 0 {
+            return Err(SaveXError::InvalidAmount);
+        }
+
+        quote_cycle(&router, &path, amount)
+    }
+
+    /// Input that maximises the profit of running the cycle `cycle` through the
+    /// configured factory's pairs, from their current reserves; 0 if no input profits
+    ///
+    /// Consecutive constant-product pools with fee γ behave like a single pool, so
+    /// the hops are folded into virtual reserves (Ea, Eb):
+    ///   Ea' = Ea * R_in / (R_in + γ * Eb),  Eb' = γ * Eb * R_out / (R_in + γ * Eb)
+    /// and the optimum of `γ * Eb * x / (Ea + γ * x) - x` is
+    ///   x* = (sqrt(γ * Ea * Eb) - Ea) / γ,  profitable only when γ * Eb > Ea.
+    /// Reserves fit in 127 bits, so every product is done in U256
+    pub fn optimal_arbitrage_amount(env: Env, cycle: Vec<Address>) -> Result<i128, SaveXError> {
+        let config = load_config(&env)?;
+
+        if !is_cycle(&cycle) {
+            return Err(SaveXError::InvalidPath);
+        }
+
+        let factory = SoroswapFactoryClient::new(&env, &config.factory);
+        let mut virtual_in = U256::from_u32(&env, 0);
+        let mut virtual_out = U256::from_u32(&env, 0);
+        for i in 0..cycle.len() - 1 {
+            let (reserve_in, reserve_out) = hop_reserves(
+                &env,
+                &factory,
+                &cycle.get(i).unwrap(),
+                &cycle.get(i + 1).unwrap(),
+            );
+
+            // An empty pool can't be traded through
+            if reserve_in <= 0 || reserve_out <= 0 {
+                return Ok(0);
+            }
+
+            let reserve_in = U256::from_u128(&env, reserve_in as u128);
+            let reserve_out = U256::from_u128(&env, reserve_out as u128);
+            if i == 0 {
+                virtual_in = reserve_in;
+                virtual_out = reserve_out;
+            } else {
+                let out_after_fee = after_fee(&env, &virtual_out);
+                let denominator = reserve_in.add(&out_after_fee);
+                virtual_in = virtual_in.mul(&reserve_in).div(&denominator);
+                virtual_out = out_after_fee.mul(&reserve_out).div(&denominator);
+            }
+        }
+
+        let out_after_fee = after_fee(&env, &virtual_out);
+        if out_after_fee <= virtual_in {
+            return Ok(0);
+        }
+
+        let root = isqrt(&env, &virtual_in.mul(&out_after_fee));
+        if root <= virtual_in {
+            return Ok(0);
+        }
+
+        let amount = root
+            .sub(&virtual_in)
+            .mul(&U256::from_u32(&env, SOROSWAP_FEE_DENOMINATOR))
+            .div(&U256::from_u32(&env, SOROSWAP_FEE_NUMERATOR));
+
+        // sqrt(γ * Ea * Eb) - Ea peaks at γ * Eb / 4, so x* <= Eb / 4, and Eb is at
+        // most the last pool's reserve; a very profitable cycle can still ask for
+        // more than the first pool holds
+        amount
+            .to_u128()
+            .and_then(|amount| i128::try_from(amount).ok())
+            .ok_or(SaveXError::InvalidAmount)
+    }
+
+    /// Quote `amount` round each candidate cycle and return the most profitable one
+    /// Candidates that aren't cycles or can't be quoted are skipped; fails with
+    /// `InvalidPath` if none is left, and with `BatchTooLarge` on more than
+    /// `MAX_CANDIDATE_CYCLES` candidates. The best cycle may still have a negative profit
+    pub fn best_cycle(env: Env, cycles: Vec<Vec<Address>>, amount: i128) -> Result<CycleSimulation, SaveXError> {
+        let router = SoroswapRouterClient::new(&env, &router_address(&env)?);
+
+        if amount <= 0 {
+            return Err(SaveXError::InvalidAmount);
+        }
+
+        if cycles.len() > MAX_CANDIDATE_CYCLES {
+            return Err(SaveXError::BatchTooLarge);
+        }
+
+        let mut best: Option<CycleSimulation> = None;
+        for path in cycles.iter() {
+            if let Ok(simulation) = quote_cycle(&router, &path, amount) {
+                if best.as_ref().is_none_or(|current| simulation.profit > current.profit) {
+                    best = Some(simulation);
+                }
+            }
+        }
+
+        best.ok_or(SaveXError::InvalidPath)
     }
 
     /// Check if arbitrage opportunity exists
@@ -193,83 +641,80 @@ impl ArbitrageBot {
         token_b: Address,
         amount: i128,
         min_profit_bps: u32,  // Minimum profit in basis points (e.g., 100 = 1%)
-    ) -> bool {
+    ) -> Result<bool, SaveXError> {
         let profit = Self::estimate_arbitrage_profit(
             env.clone(),
             token_a,
             token_b,
             amount,
-        );
+        )?;
 
         let min_profit = (amount * min_profit_bps as i128) / 10000;
 
-        profit >= min_profit
+        Ok(profit >= min_profit)
     }
 
-    /// Flash arbitrage: borrow, arbitrage, repay in single transaction
-    /// This is atomic - either all succeeds or all reverts
-    pub fn flash_arbitrage(
+    /// Flash-loan arbitrage callback
+    /// Soroban forbids re-entering a contract, so the bot can't call the lender and
+    /// then take its callback: an executor borrows by calling the configured
+    /// lender's `flash_loan` with this contract as receiver and `FlashArbitrageParams`
+    /// as `data`. The loan is swapped round `params.path`, which must start and end
+    /// in the borrowed token, and `amount + fee` is repaid; the bot keeps the surplus
+    /// for the admin to withdraw.
+    /// The router reverts the whole loan unless the cycle also clears `min_profit`
+    pub fn on_flash_loan(
         env: Env,
-        executor: Address,
-        borrow_token: Address,
-        borrow_amount: i128,
-        swap_path: Vec<Address>,
-        min_profit: i128,
-    ) -> i128 {
-        executor.require_auth();
-
-        // Note: True flash loans require integration with lending protocol
-        // For now, this assumes executor has funds
-        
-        // Transfer borrowed amount
-        token::Client::new(&env, &borrow_token).transfer(
-            &executor,
-            &env.current_contract_address(),
-            &borrow_amount
-        );
+        token: Address,
+        amount: i128,
+        fee: i128,
+        data: Bytes,
+    ) -> Result<(), SaveXError> {
+        let config = load_config(&env)?;
+        let lender = config.flash_lender.clone().ok_or(SaveXError::NotAuthorized)?;
+        lender.require_auth();
+
+        let params = FlashArbitrageParams::from_xdr(&env, &data)
+            .map_err(|_| SaveXError::InvalidPath)?;
+        require_executor(&env, &params.executor)?;
+
+        if amount <= 0 || fee < 0 || params.min_profit < 0 {
+            return Err(SaveXError::InvalidAmount);
+        }
 
-        // Execute multi-hop swap
-        let router_address: Address = env
-            .storage()
-            .instance()
-            .get(&DataKey::RouterAddress)
-            .expect("Router not configured");
+        if !is_cycle(&params.path) || params.path.get(0).unwrap() != token {
+            return Err(SaveXError::InvalidPath);
+        }
 
-        let router = SoroswapRouterClient::new(&env, &router_address);
-        let deadline = env.ledger().timestamp() + 300;
+        // Only trade what the lender actually sent, never the retained profit
+        let token_client = token::Client::new(&env, &token);
+        let held = retained(&env, &token);
+        if token_client.balance(&env.current_contract_address()) - held < amount {
+            return Err(SaveXError::InsufficientBalance);
+        }
 
-        let amounts = router.swap_exact_tokens_for_tokens(
-            &borrow_amount,
-            &0,
-            &swap_path,
-            &env.current_contract_address(),
-            &deadline,
-        );
+        let final_output = swap(
+            &env,
+            &default_venue(&config),
+            &params.path,
+            amount,
+            amount + fee + params.min_profit,
+        )?;
 
-        let final_output = amounts.get(amounts.len() - 1).unwrap();
+        let profit = final_output - amount - fee;
 
-        // Repay borrow + profit check
-        let profit = final_output - borrow_amount;
+        // Repay the lender and keep the surplus
+        token_client.transfer(&env.current_contract_address(), &lender, &(amount + fee));
+        store_retained(&env, &token, held + profit);
 
-        if profit < min_profit {
-            panic!("Insufficient profit");
+        events::ArbitrageExecuted {
+            executor: params.executor,
+            path: params.path,
+            amount_in: amount,
+            amount_out: final_output,
+            profit,
         }
+        .publish(&env);
 
-        // Return borrowed amount to executor
-        token::Client::new(&env, &borrow_token).transfer(
-            &env.current_contract_address(),
-            &executor,
-            &borrow_amount
-        );
-
-        // Return profit to executor
-        let profit_token = swap_path.get(swap_path.len() - 1).unwrap();
-        token::Client::new(&env, &profit_token).transfer(
-            &env.current_contract_address(),
-            &executor,
-            &profit
-        );
-
-        profit
+        Ok(())
     }
 }
@@ -0,0 +1,513 @@
+//! Contract events emitted by SaveX
+//!
+//! Every state transition publishes one of these so indexers can follow
+//! transfers, rate locks, packages and admin actions without polling.
+
+use soroban_sdk::{contractevent, Address, Env, String, Vec};
+use crate::types::*;
+
+// ============================================================================
+// Transfer Events
+// ============================================================================
+
+/// A transfer record was created (any transfer type)
+#[contractevent(topics = ["transfer", "created"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferCreated {
+    pub id: u64,
+    pub from: Address,
+    pub to: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub transfer_type: TransferType,
+    pub execute_after: u64,     // 0 for transfers without a time bound
+}
+
+/// Tokens of a transfer reached the recipient
+#[contractevent(topics = ["transfer", "executed"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferExecuted {
+    pub id: u64,
+    pub from: Address,
+    pub to: Address,
+    pub token: Address,
+    pub amount: i128,
+}
+
+/// A locked transfer was cancelled and refunded to the sender
+#[contractevent(topics = ["transfer", "cancelled"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TransferCancelled {
+    pub id: u64,
+    pub from: Address,
+    pub to: Address,
+    pub token: Address,
+    pub amount: i128,
+}
+
+/// A transfer fee was sent to the treasury
+#[contractevent(topics = ["fee", "charged"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeeCharged {
+    pub transfer_id: u64,
+    pub payer: Address,
+    pub token: Address,
+    pub fee: i128,
+}
+
+// ============================================================================
+// Stream Events
+// ============================================================================
+
+#[contractevent(topics = ["stream", "created"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamCreated {
+    pub id: u64,
+    pub start: u64,
+    pub end: u64,
+}
+
+/// The recipient withdrew vested tokens from a stream
+#[contractevent(topics = ["stream", "withdrawn"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamWithdrawn {
+    pub id: u64,
+    pub to: Address,
+    pub amount: i128,
+    pub withdrawn: i128,           // Total withdrawn so far
+}
+
+/// A stream was cancelled; vested tokens went to the recipient, the rest back to the sender
+#[contractevent(topics = ["stream", "cancelled"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct StreamCancelled {
+    pub id: u64,
+    pub paid_out: i128,
+    pub refund: i128,
+}
+
+// ============================================================================
+// Batch Events
+// ============================================================================
+
+#[contractevent(topics = ["batch", "created"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchCreated {
+    pub id: u64,
+    pub creator: Address,
+    pub transfer_count: u32,
+}
+
+/// Due legs of a batch were executed in one call
+#[contractevent(topics = ["batch", "executed"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchExecuted {
+    pub id: u64,
+    pub executed: u32,
+    pub pending_legs: u32,
+}
+
+/// A batch was cancelled; `cancelled` counts the locked legs refunded
+#[contractevent(topics = ["batch", "cancelled"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchCancelled {
+    pub id: u64,
+    pub creator: Address,
+    pub cancelled: u32,
+}
+
+// ============================================================================
+// Escrow Events
+// ============================================================================
+
+#[contractevent(topics = ["escrow", "created"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowCreated {
+    pub id: u64,
+    pub release: EscrowRelease,
+    pub refund_after: u64,
+}
+
+#[contractevent(topics = ["escrow", "approved"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowApproved {
+    pub id: u64,
+    pub approver: Address,
+    pub approvals: u32,
+}
+
+#[contractevent(topics = ["escrow", "disputed"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowDisputed {
+    pub id: u64,
+    pub raised_by: Address,
+}
+
+/// The arbiter settled a dispute; `released` is false when refunded
+#[contractevent(topics = ["escrow", "resolved"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct EscrowResolved {
+    pub id: u64,
+    pub arbiter: Address,
+    pub released: bool,
+}
+
+// ============================================================================
+// Recurring Transfer Events
+// ============================================================================
+
+#[contractevent(topics = ["recurring", "created"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecurringCreated {
+    pub id: u64,
+    pub from: Address,
+    pub to: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub interval: u64,
+    pub occurrences: u32,
+    pub first_execution: u64,
+}
+
+/// One installment of a recurring transfer was paid out
+#[contractevent(topics = ["recurring", "installment"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecurringInstallmentExecuted {
+    pub id: u64,
+    pub transfer_id: u64,
+    pub installment: u32,          // 1-based
+    pub next_execution: u64,       // 0 once the schedule is completed
+}
+
+#[contractevent(topics = ["recurring", "paused"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecurringPaused {
+    pub id: u64,
+    pub from: Address,
+}
+
+#[contractevent(topics = ["recurring", "resumed"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecurringResumed {
+    pub id: u64,
+    pub from: Address,
+    pub next_execution: u64,
+}
+
+#[contractevent(topics = ["recurring", "cancelled"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RecurringCancelled {
+    pub id: u64,
+    pub from: Address,
+    pub refund: i128,              // Escrow of the installments not yet paid
+}
+
+// ============================================================================
+// Rate Lock Events
+// ============================================================================
+
+#[contractevent(topics = ["rate_lock", "created"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateLockCreated {
+    pub id: u64,
+    pub owner: Address,
+    pub from_token: Address,
+    pub to_token: Address,
+    pub locked_rate: i128,
+    pub amount: i128,
+    pub premium: i128,
+    pub expiry: u64,
+}
+
+/// A transfer consumed a rate lock
+#[contractevent(topics = ["rate_lock", "used"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateLockUsed {
+    pub id: u64,
+    pub owner: Address,
+    pub transfer_id: u64,
+    pub amount: i128,              // Input, in `from_token`
+    pub amount_out: i128,          // Delivered at the locked rate, in `to_token`
+    pub remaining_amount: i128,    // Lock is deactivated once this reaches 0
+}
+
+/// The hedge reserve of a token changed; `delta` is negative when drawn
+#[contractevent(topics = ["rate_lock", "reserve"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct HedgeReserveChanged {
+    pub token: Address,
+    pub delta: i128,
+    pub balance: i128,
+}
+
+/// The admin set the reference rate locks of a token pair are checked against
+#[contractevent(topics = ["rate_lock", "reference"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReferenceRateUpdated {
+    pub from_token: Address,
+    pub to_token: Address,
+    pub rate: i128,
+}
+
+#[contractevent(topics = ["rate_lock", "cancelled"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateLockCancelled {
+    pub id: u64,
+    pub owner: Address,
+    pub refund: i128,              // Unused escrow returned to the owner
+}
+
+/// An expired lock was closed and its unused escrow returned
+#[contractevent(topics = ["rate_lock", "expired"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RateLockExpired {
+    pub id: u64,
+    pub owner: Address,
+    pub refund: i128,
+    pub keeper_reward: i128,       // Share of the escrow paid to a sweeping keeper
+}
+
+// ============================================================================
+// Package Events
+// ============================================================================
+
+#[contractevent(topics = ["package", "subscribed"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageSubscribed {
+    pub owner: Address,
+    pub package_type: PackageType,
+    pub discount_rate: u32,
+    pub start_date: u64,
+    pub end_date: u64,
+    pub amount_paid: i128,
+}
+
+#[contractevent(topics = ["package", "renewed"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageRenewed {
+    pub owner: Address,
+    pub package_type: PackageType,
+    pub end_date: u64,
+    pub amount_paid: i128,
+}
+
+/// A package moved to another tier for the rest of its term
+#[contractevent(topics = ["package", "changed"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageChanged {
+    pub owner: Address,
+    pub from_type: PackageType,
+    pub to_type: PackageType,
+    pub amount_paid: i128,
+    pub refund: i128,
+}
+
+#[contractevent(topics = ["package", "cancelled"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageCancelled {
+    pub owner: Address,
+    pub package_type: PackageType,
+    pub refund: i128,              // Unused days returned to the owner
+}
+
+#[contractevent(topics = ["package", "member_added"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageMemberAdded {
+    pub owner: Address,
+    pub member: Address,
+}
+
+#[contractevent(topics = ["package", "member_removed"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageMemberRemoved {
+    pub owner: Address,
+    pub member: Address,
+}
+
+/// A transfer went over the package quota and paid the standard fee
+#[contractevent(topics = ["package", "quota"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageQuotaExceeded {
+    pub owner: Address,
+    pub package_type: PackageType,
+    pub period_transfer_count: u32,
+    pub period_volume: i128,
+}
+
+#[contractevent(topics = ["package", "tier"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackageTierUpdated {
+    pub admin: Address,
+    pub package_type: PackageType,
+    pub tier: PackageTier,
+}
+
+#[contractevent(topics = ["package", "price"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct PackagePriceUpdated {
+    pub admin: Address,
+    pub package_type: PackageType,
+    pub token: Address,
+    pub price_per_day: i128,
+}
+
+// ============================================================================
+// Admin Events
+// ============================================================================
+
+#[contractevent(topics = ["admin", "init"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Initialized {
+    pub admin: Address,
+}
+
+#[contractevent(topics = ["admin", "paused"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Paused {
+    pub admin: Address,
+}
+
+#[contractevent(topics = ["admin", "unpaused"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct Unpaused {
+    pub admin: Address,
+}
+
+#[contractevent(topics = ["admin", "router"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct RouterUpdated {
+    pub admin: Address,
+    pub router: Address,
+}
+
+#[contractevent(topics = ["admin", "factory"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FactoryUpdated {
+    pub admin: Address,
+    pub factory: Address,
+}
+
+#[contractevent(topics = ["admin", "fees"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct FeeStructureUpdated {
+    pub admin: Address,
+    pub fee_structure: FeeStructure,
+}
+
+#[contractevent(topics = ["admin", "treasury"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TreasuryUpdated {
+    pub admin: Address,
+    pub treasury: Address,
+}
+
+// ============================================================================
+// Token Registry Events
+// ============================================================================
+
+#[contractevent(topics = ["token", "registered"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenRegistered {
+    pub admin: Address,
+    pub token: Address,
+    pub symbol: String,
+    pub decimals: u32,
+}
+
+#[contractevent(topics = ["token", "supported"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenSupportUpdated {
+    pub admin: Address,
+    pub token: Address,
+    pub is_supported: bool,
+}
+
+#[contractevent(topics = ["token", "fees"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct TokenFeeStructureUpdated {
+    pub admin: Address,
+    pub token: Address,
+    pub fee_structure: FeeStructure,
+}
+
+// ============================================================================
+// Arbitrage Events
+// ============================================================================
+
+/// An arbitrage trade completed; `profit` is in the starting token
+#[contractevent(topics = ["arb", "executed"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArbitrageExecuted {
+    pub executor: Address,
+    pub path: Vec<Address>,
+    pub amount_in: i128,
+    pub amount_out: i128,
+    pub profit: i128,
+}
+
+#[contractevent(topics = ["arb", "config"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArbitrageConfigUpdated {
+    pub admin: Address,
+    pub config: ArbitrageConfig,
+}
+
+#[contractevent(topics = ["arb", "executor"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArbitrageExecutorUpdated {
+    pub admin: Address,
+    pub executor: Address,
+    pub allowed: bool,
+}
+
+/// Retained flash-loan profit was paid out by the admin
+#[contractevent(topics = ["arb", "withdrawn"])]
+#[derive(Clone, Debug, PartialEq)]
+pub struct ArbitrageProfitWithdrawn {
+    pub admin: Address,
+    pub token: Address,
+    pub to: Address,
+    pub amount: i128,
+}
+
+// ============================================================================
+// Helpers
+// ============================================================================
+
+/// Publish `TransferCreated` for a freshly stored transfer
+pub(crate) fn transfer_created(env: &Env, transfer: &Transfer) {
+    TransferCreated {
+        id: transfer.id,
+        from: transfer.from.clone(),
+        to: transfer.to.clone(),
+        token: transfer.token.clone(),
+        amount: transfer.amount,
+        transfer_type: transfer.transfer_type.clone(),
+        execute_after: if transfer.has_time_bound { transfer.time_bound_timestamp } else { 0 },
+    }
+    .publish(env);
+}
+
+/// Publish `TransferExecuted` once tokens have reached the recipient
+pub(crate) fn transfer_executed(env: &Env, transfer: &Transfer) {
+    TransferExecuted {
+        id: transfer.id,
+        from: transfer.from.clone(),
+        to: transfer.to.clone(),
+        token: transfer.token.clone(),
+        amount: transfer.amount,
+    }
+    .publish(env);
+}
+
+/// Publish `TransferCancelled` once tokens have been refunded
+pub(crate) fn transfer_cancelled(env: &Env, transfer: &Transfer) {
+    TransferCancelled {
+        id: transfer.id,
+        from: transfer.from.clone(),
+        to: transfer.to.clone(),
+        token: transfer.token.clone(),
+        amount: transfer.amount,
+    }
+    .publish(env);
+}
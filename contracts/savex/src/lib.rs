@@ -7,8 +7,12 @@
 
 mod types;
 mod arbitrage;
+pub mod events;
 
-use soroban_sdk::{contract, contractclient, contractimpl, token, Address, Env, Vec};
+use soroban_sdk::{
+    auth::{ContractContext, InvokerContractAuthEntry, SubContractInvocation},
+    contract, contractclient, contractimpl, token, vec, Address, Bytes, BytesN, Env, IntoVal, Map, String, Symbol, Vec,
+};
 
 pub use types::*;
 pub use arbitrage::*;
@@ -57,11 +61,56 @@ pub trait SoroswapPair {
     fn token_1(env: Env) -> Address;
 }
 
+/// Flash loan provider interface
+/// The lender sends `amount` of `token` to `receiver`, calls its `on_flash_loan`
+/// and reverts unless it holds the amount plus its fee again by the end of the call.
+/// Soroban forbids re-entry, so the loan is started by the borrower's caller
+#[contractclient(name = "FlashLenderClient")]
+pub trait FlashLender {
+    /// Lend `amount` of `token` to `receiver` for the duration of the call
+    fn flash_loan(env: Env, receiver: Address, token: Address, amount: i128, data: Bytes);
+}
+
+/// Flash loan callback, implemented by the borrower
+#[contractclient(name = "FlashBorrowerClient")]
+pub trait FlashBorrower {
+    /// Use the loan, then pay `amount + fee` of `token` back to the lender
+    fn on_flash_loan(env: Env, token: Address, amount: i128, fee: i128, data: Bytes);
+}
+
 // Storage TTL constants
 const DAY_IN_LEDGERS: u32 = 17280; // Approximately 1 day
 const INSTANCE_BUMP_AMOUNT: u32 = 7 * DAY_IN_LEDGERS; // 7 days
 const INSTANCE_LIFETIME_THRESHOLD: u32 = INSTANCE_BUMP_AMOUNT - DAY_IN_LEDGERS;
 
+// Token registry constants
+const FEE_DECIMALS: u32 = 7;        // Default fee structure is in XLM stroops
+const MAX_TOKEN_DECIMALS: u32 = 18;
+
+// Rate lock constants
+const RATE_PRECISION: i128 = 10_000_000;   // Locked rates have 7 decimals
+const LOCK_PREMIUM_BASE_BPS: i128 = 10;    // 0.1% for any lock
+const LOCK_PREMIUM_BPS_PER_HOUR: i128 = 2; // Plus 0.02% per started hour
+const KEEPER_REWARD_BPS: i128 = 10;        // 0.1% of swept escrow goes to the keeper
+const MAX_RATE_DEVIATION_BPS: i128 = 200;  // Locked rates stay within 2% of the reference rate
+const MAX_SWEEP_SIZE: u32 = 50;
+
+// Package constants
+const PACKAGE_PERIOD: u64 = 30 * 86400;  // Monthly usage period for quotas
+const MAX_DISCOUNT_STEPS: u32 = 10;
+
+// Index constants
+const MAX_PAGE_SIZE: u32 = 50;
+
+// Batch constants
+const MAX_BATCH_SIZE: u32 = 50;
+
+// Recurring transfer constants
+const MAX_OCCURRENCES: u32 = 120;
+
+// Escrow constants
+const MAX_APPROVERS: u32 = 10;
+
 // ============================================================================
 // Contract Definition
 // ============================================================================
@@ -86,6 +135,270 @@ fn is_paused(env: &Env) -> bool {
         .unwrap_or(false)
 }
 
+/// Fail with `ContractPaused` while the contract is paused
+fn require_not_paused(env: &Env) -> Result<(), SaveXError> {
+    if is_paused(env) {
+        return Err(SaveXError::ContractPaused);
+    }
+    Ok(())
+}
+
+/// Require auth from `admin` and check it matches the stored admin
+fn require_admin(env: &Env, admin: &Address) -> Result<(), SaveXError> {
+    admin.require_auth();
+
+    let stored_admin: Address = env.storage()
+        .instance()
+        .get(&DataKey::Admin)
+        .ok_or(SaveXError::NotInitialized)?;
+
+    if *admin != stored_admin {
+        return Err(SaveXError::NotAuthorized);
+    }
+
+    Ok(())
+}
+
+/// Load a transfer record
+fn load_transfer(env: &Env, transfer_id: u64) -> Result<Transfer, SaveXError> {
+    env.storage()
+        .persistent()
+        .get(&PersistentDataKey::Transfer(transfer_id))
+        .ok_or(SaveXError::TransferNotFound)
+}
+
+/// Load the release conditions of an escrow transfer
+fn load_escrow_terms(env: &Env, transfer_id: u64) -> Result<EscrowTerms, SaveXError> {
+    env.storage()
+        .persistent()
+        .get(&PersistentDataKey::Escrow(transfer_id))
+        .ok_or(SaveXError::TransferNotFound)
+}
+
+/// Helper: Store escrow terms in persistent storage with TTL
+fn store_escrow_terms(env: &Env, terms: &EscrowTerms) {
+    let key = PersistentDataKey::Escrow(terms.transfer_id);
+    env.storage().persistent().set(&key, terms);
+    env.storage().persistent().extend_ttl(&key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+/// Pay out a locked transfer to its recipient, or refund it to the sender
+fn settle_locked_transfer(env: &Env, transfer: &mut Transfer, release: bool) {
+    let receiver = if release { &transfer.to } else { &transfer.from };
+    token::Client::new(env, &transfer.token).transfer(
+        &env.current_contract_address(),
+        receiver,
+        &transfer.amount
+    );
+
+    transfer.status = if release { TransferStatus::Completed } else { TransferStatus::Cancelled };
+    store_transfer(env, transfer);
+    if transfer.batch_id != 0 {
+        settle_batch_leg(env, transfer.batch_id);
+    }
+
+    if release {
+        events::transfer_executed(env, transfer);
+    } else {
+        events::transfer_cancelled(env, transfer);
+    }
+}
+
+/// Load the vesting schedule of a stream transfer
+fn load_stream(env: &Env, transfer_id: u64) -> Result<StreamSchedule, SaveXError> {
+    env.storage()
+        .persistent()
+        .get(&PersistentDataKey::Stream(transfer_id))
+        .ok_or(SaveXError::TransferNotFound)
+}
+
+/// Helper: Store stream schedule in persistent storage with TTL
+fn store_stream(env: &Env, stream: &StreamSchedule) {
+    let key = PersistentDataKey::Stream(stream.transfer_id);
+    env.storage().persistent().set(&key, stream);
+    env.storage().persistent().extend_ttl(&key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+/// Amount of a stream vested at the current ledger time
+fn vested_amount(env: &Env, transfer: &Transfer, stream: &StreamSchedule) -> i128 {
+    let now = env.ledger().timestamp();
+    if now <= stream.start {
+        return 0;
+    }
+    if now >= stream.end {
+        return transfer.amount;
+    }
+
+    let elapsed = (now - stream.start) as i128;
+    let duration = (stream.end - stream.start) as i128;
+    transfer.amount * elapsed / duration
+}
+
+/// Load a batch record
+fn load_batch(env: &Env, batch_id: u64) -> Result<Batch, SaveXError> {
+    env.storage()
+        .persistent()
+        .get(&PersistentDataKey::Batch(batch_id))
+        .ok_or(SaveXError::TransferNotFound)
+}
+
+/// Helper: Store batch in persistent storage with TTL
+fn store_batch(env: &Env, batch: &Batch) {
+    let key = PersistentDataKey::Batch(batch.id);
+    env.storage().persistent().set(&key, batch);
+    env.storage().persistent().extend_ttl(&key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+/// Get the next batch ID
+fn next_batch_id(env: &Env) -> u64 {
+    let current: u64 = env.storage()
+        .instance()
+        .get(&DataKey::BatchCounter)
+        .unwrap_or(0);
+    let next = current + 1;
+    env.storage().instance().set(&DataKey::BatchCounter, &next);
+    next
+}
+
+/// Start a batch record with the next batch ID; legs are added before it is saved
+fn new_batch(env: &Env, creator: &Address) -> Batch {
+    Batch {
+        id: next_batch_id(env),
+        creator: creator.clone(),
+        transfer_ids: Vec::new(env),
+        totals: Map::new(env),
+        pending_legs: 0,
+        status: BatchStatus::Pending,
+        created_at: env.ledger().timestamp(),
+    }
+}
+
+/// Link a transfer to its batch and store it; `gross_amount` includes the fee
+fn save_batch_leg(env: &Env, batch: &mut Batch, transfer: &mut Transfer, gross_amount: i128) {
+    transfer.batch_id = batch.id;
+    save_new_transfer(env, transfer);
+
+    batch.transfer_ids.push_back(transfer.id);
+    let total = batch.totals.get(transfer.token.clone()).unwrap_or(0);
+    batch.totals.set(transfer.token.clone(), total + gross_amount);
+    if transfer.status == TransferStatus::Locked {
+        batch.pending_legs += 1;
+    }
+}
+
+/// Store a new batch and publish `BatchCreated`
+fn save_new_batch(env: &Env, batch: &mut Batch) {
+    if batch.pending_legs == 0 {
+        batch.status = BatchStatus::Settled;
+    }
+    store_batch(env, batch);
+
+    events::BatchCreated {
+        id: batch.id,
+        creator: batch.creator.clone(),
+        transfer_count: batch.transfer_ids.len(),
+    }
+    .publish(env);
+}
+
+/// Count a locked leg of a batch as executed or cancelled
+fn settle_batch_leg(env: &Env, batch_id: u64) {
+    if let Ok(mut batch) = load_batch(env, batch_id) {
+        batch.pending_legs -= 1;
+        if batch.pending_legs == 0 && batch.status == BatchStatus::Pending {
+            batch.status = BatchStatus::Settled;
+        }
+        store_batch(env, &batch);
+    }
+}
+
+/// Check whether a transfer is a scheduled leg, released in full once its time comes
+fn is_scheduled_leg(transfer: &Transfer) -> bool {
+    transfer.has_time_bound
+        && transfer.time_bound_kind == TimeBoundKind::After
+        && transfer.transfer_type != TransferType::Stream
+}
+
+/// Check whether a locked transfer is a scheduled leg that can run now
+fn is_due_scheduled(env: &Env, transfer: &Transfer) -> bool {
+    let time_bound = TimeBound {
+        kind: transfer.time_bound_kind.clone(),
+        timestamp: transfer.time_bound_timestamp,
+    };
+
+    transfer.status == TransferStatus::Locked
+        && is_scheduled_leg(transfer)
+        && check_time_bound(env, &time_bound)
+}
+
+/// Load a recurring transfer record
+fn load_recurring(env: &Env, recurring_id: u64) -> Result<RecurringTransfer, SaveXError> {
+    env.storage()
+        .persistent()
+        .get(&PersistentDataKey::Recurring(recurring_id))
+        .ok_or(SaveXError::TransferNotFound)
+}
+
+/// Helper: Store recurring transfer in persistent storage with TTL
+fn store_recurring(env: &Env, recurring: &RecurringTransfer) {
+    let key = PersistentDataKey::Recurring(recurring.id);
+    env.storage().persistent().set(&key, recurring);
+    env.storage().persistent().extend_ttl(&key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+/// Get the next recurring transfer ID
+fn next_recurring_id(env: &Env) -> u64 {
+    let current: u64 = env.storage()
+        .instance()
+        .get(&DataKey::RecurringCounter)
+        .unwrap_or(0);
+    let next = current + 1;
+    env.storage().instance().set(&DataKey::RecurringCounter, &next);
+    next
+}
+
+/// Load a rate lock record
+fn load_rate_lock(env: &Env, lock_id: u64) -> Result<RateLock, SaveXError> {
+    env.storage()
+        .persistent()
+        .get(&PersistentDataKey::RateLock(lock_id))
+        .ok_or(SaveXError::RateLockNotFound)
+}
+
+/// Helper: Store rate lock in persistent storage with TTL
+/// Closed locks are not bumped so their entries can expire naturally
+fn store_rate_lock(env: &Env, rate_lock: &RateLock) {
+    let key = PersistentDataKey::RateLock(rate_lock.id);
+    env.storage().persistent().set(&key, rate_lock);
+    if rate_lock.is_active {
+        env.storage().persistent().extend_ttl(&key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+    }
+}
+
+/// Get configured Soroswap Router address
+fn router_address(env: &Env) -> Result<Address, SaveXError> {
+    env.storage()
+        .instance()
+        .get(&DataKey::RouterAddress)
+        .ok_or(SaveXError::RouterNotConfigured)
+}
+
+/// Get configured treasury address
+fn treasury_address(env: &Env) -> Result<Address, SaveXError> {
+    env.storage()
+        .instance()
+        .get(&DataKey::Treasury)
+        .ok_or(SaveXError::TreasuryNotConfigured)
+}
+
+/// Get configured Soroswap Factory address
+fn factory_address(env: &Env) -> Result<Address, SaveXError> {
+    env.storage()
+        .instance()
+        .get(&DataKey::FactoryAddress)
+        .ok_or(SaveXError::FactoryNotConfigured)
+}
+
 /// Helper: Store transfer in persistent storage with TTL
 fn store_transfer(env: &Env, transfer: &Transfer) {
     let key = PersistentDataKey::Transfer(transfer.id);
@@ -104,6 +417,221 @@ fn next_transfer_id(env: &Env) -> u64 {
     next
 }
 
+/// Build a new transfer record with the next transfer ID
+/// `execute_after` of 0 means the transfer completes immediately,
+/// otherwise the transfer is locked until that timestamp
+fn new_transfer(
+    env: &Env,
+    from: &Address,
+    to: &Address,
+    token: &Address,
+    amount: i128,
+    transfer_type: TransferType,
+    execute_after: u64,
+) -> Transfer {
+    let is_scheduled = execute_after > 0;
+
+    Transfer {
+        id: next_transfer_id(env),
+        from: from.clone(),
+        to: to.clone(),
+        token: token.clone(),
+        amount,
+        transfer_type,
+        has_time_bound: is_scheduled,
+        time_bound_kind: TimeBoundKind::After,
+        time_bound_timestamp: execute_after,
+        status: if is_scheduled { TransferStatus::Locked } else { TransferStatus::Completed },
+        created_at: env.ledger().timestamp(),
+        has_rate_lock: false,
+        rate_lock_id: 0,
+        claim_hash: BytesN::from_array(env, &[0; 32]),
+        batch_id: 0,
+    }
+}
+
+/// Charge the transfer fee and escrow the net amount in the contract
+/// Returns the escrowed amount and the fee
+fn escrow_transfer_amount(
+    env: &Env,
+    from: &Address,
+    token: &Address,
+    amount: i128,
+) -> Result<(i128, i128), SaveXError> {
+    if amount <= 0 {
+        return Err(SaveXError::InvalidAmount);
+    }
+
+    let token_info = require_supported_token(env, token)?;
+    let fee = collect_fee(env, from, &token_info, amount, false, 1)?;
+    let net_amount = amount - fee;
+    record_package_usage(env, from, amount);
+
+    // Lock tokens in contract
+    token::Client::new(env, token).transfer(
+        from,
+        env.current_contract_address(),
+        &net_amount
+    );
+
+    Ok((net_amount, fee))
+}
+
+/// Append a record ID to a per-user index
+fn push_index(env: &Env, index: UserIndex, id: u64) {
+    let length_key = PersistentDataKey::IndexLength(index.clone());
+    let length: u64 = env.storage().persistent().get(&length_key).unwrap_or(0);
+
+    let entry_key = PersistentDataKey::IndexEntry(index, length);
+    env.storage().persistent().set(&entry_key, &id);
+    env.storage().persistent().extend_ttl(&entry_key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+
+    env.storage().persistent().set(&length_key, &(length + 1));
+    env.storage().persistent().extend_ttl(&length_key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+/// Read up to `limit` record IDs of a per-user index starting at `cursor`
+fn read_index(env: &Env, index: UserIndex, cursor: u64, limit: u32) -> Vec<u64> {
+    let length: u64 = env.storage()
+        .persistent()
+        .get(&PersistentDataKey::IndexLength(index.clone()))
+        .unwrap_or(0);
+    let end = length.min(cursor.saturating_add(limit.min(MAX_PAGE_SIZE) as u64));
+
+    let mut ids = Vec::new(env);
+    for position in cursor..end {
+        if let Some(id) = env.storage()
+            .persistent()
+            .get(&PersistentDataKey::IndexEntry(index.clone(), position))
+        {
+            ids.push_back(id);
+        }
+    }
+    ids
+}
+
+/// Index a locked or claimable transfer as pending for its sender and recipient
+/// Entries are never rewritten; readers skip transfers that have since settled
+fn add_pending_transfer(env: &Env, transfer: &Transfer) {
+    push_index(env, UserIndex::Pending(transfer.from.clone()), transfer.id);
+    if transfer.to != transfer.from {
+        push_index(env, UserIndex::Pending(transfer.to.clone()), transfer.id);
+    }
+}
+
+/// Whether a transfer is still waiting to settle
+fn is_unsettled(transfer: &Transfer) -> bool {
+    matches!(
+        transfer.status,
+        TransferStatus::Locked | TransferStatus::Claimable | TransferStatus::Disputed
+    )
+}
+
+/// Load transfer records by ID, skipping any that have expired from storage
+fn load_transfers(env: &Env, ids: Vec<u64>) -> Vec<Transfer> {
+    let mut transfers = Vec::new(env);
+    for id in ids.iter() {
+        if let Ok(transfer) = load_transfer(env, id) {
+            transfers.push_back(transfer);
+        }
+    }
+    transfers
+}
+
+/// Store a newly created transfer, index it and publish its events
+fn save_new_transfer(env: &Env, transfer: &Transfer) {
+    store_transfer(env, transfer);
+    push_index(env, UserIndex::Sent(transfer.from.clone()), transfer.id);
+    if transfer.status == TransferStatus::Claimable {
+        // Indexed for the recipient once claimed
+        add_pending_transfer(env, transfer);
+    } else {
+        push_index(env, UserIndex::Received(transfer.to.clone()), transfer.id);
+        if transfer.status == TransferStatus::Locked {
+            add_pending_transfer(env, transfer);
+        }
+    }
+
+    events::transfer_created(env, transfer);
+
+    if transfer.status == TransferStatus::Completed {
+        events::transfer_executed(env, transfer);
+    }
+}
+
+/// Premium for locking `amount` for `duration_seconds`, in the locked token
+fn lock_premium(amount: i128, duration_seconds: u64) -> i128 {
+    let hours = duration_seconds.div_ceil(3600) as i128;
+    let premium_bps = LOCK_PREMIUM_BASE_BPS + LOCK_PREMIUM_BPS_PER_HOUR * hours;
+    (amount * premium_bps) / 10000
+}
+
+/// Deactivate a rate lock and refund its unused escrow to the owner
+/// Returns the amount refunded
+fn close_rate_lock(env: &Env, rate_lock: &mut RateLock) -> i128 {
+    let refund = rate_lock.remaining_amount;
+    if refund > 0 {
+        token::Client::new(env, &rate_lock.from_token).transfer(
+            &env.current_contract_address(),
+            &rate_lock.owner,
+            &refund,
+        );
+    }
+
+    rate_lock.remaining_amount = 0;
+    rate_lock.is_active = false;
+    store_rate_lock(env, rate_lock);
+
+    refund
+}
+
+/// Get the hedge reserve held for a token
+fn hedge_reserve(env: &Env, token: &Address) -> i128 {
+    env.storage()
+        .instance()
+        .get(&DataKey::HedgeReserve(token.clone()))
+        .unwrap_or(0)
+}
+
+/// Check a pool rate is within `MAX_RATE_DEVIATION_BPS` of the pair's reference rate
+/// so a pool pushed off its price within the transaction can't set or fill a lock
+/// at a rate the hedge reserve has to honour; fails with `InvalidRate` if the
+/// admin has not set a reference rate for the pair
+fn require_near_reference_rate(
+    env: &Env,
+    from_token: &Address,
+    to_token: &Address,
+    rate: i128,
+) -> Result<(), SaveXError> {
+    let reference_rate: i128 = env.storage()
+        .instance()
+        .get(&DataKey::ReferenceRate(from_token.clone(), to_token.clone()))
+        .ok_or(SaveXError::InvalidRate)?;
+    if (rate - reference_rate).abs() * 10000 > reference_rate * MAX_RATE_DEVIATION_BPS {
+        return Err(SaveXError::SlippageExceeded);
+    }
+    Ok(())
+}
+
+/// Add `delta` (negative to draw) to the hedge reserve of a token
+fn update_hedge_reserve(env: &Env, token: &Address, delta: i128) -> Result<(), SaveXError> {
+    let balance = hedge_reserve(env, token) + delta;
+    if balance < 0 {
+        return Err(SaveXError::InsufficientHedgeReserve);
+    }
+
+    env.storage().instance().set(&DataKey::HedgeReserve(token.clone()), &balance);
+
+    events::HedgeReserveChanged {
+        token: token.clone(),
+        delta,
+        balance,
+    }
+    .publish(env);
+
+    Ok(())
+}
+
 /// Get the next rate lock ID
 fn next_rate_lock_id(env: &Env) -> u64 {
     let current: u64 = env.storage()
@@ -125,6 +653,87 @@ fn check_time_bound(env: &Env, time_bound: &TimeBound) -> bool {
     }
 }
 
+/// Load a token from the registry
+fn load_token(env: &Env, token: &Address) -> Result<TokenInfo, SaveXError> {
+    env.storage()
+        .persistent()
+        .get(&PersistentDataKey::Token(token.clone()))
+        .ok_or(SaveXError::TokenNotRegistered)
+}
+
+/// Helper: Store token registry entry in persistent storage with TTL
+fn store_token(env: &Env, info: &TokenInfo) {
+    let key = PersistentDataKey::Token(info.address.clone());
+    env.storage().persistent().set(&key, info);
+    env.storage().persistent().extend_ttl(&key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+/// Fail with `TokenNotSupported` unless the token is registered and supported
+fn require_supported_token(env: &Env, token: &Address) -> Result<TokenInfo, SaveXError> {
+    match load_token(env, token) {
+        Ok(info) if info.is_supported => Ok(info),
+        _ => Err(SaveXError::TokenNotSupported),
+    }
+}
+
+/// Default fee structure, denominated in stroops (7 decimals)
+fn default_fee_structure() -> FeeStructure {
+    FeeStructure {
+        base_fee: 1000000,          // 0.1 XLM (1 XLM = 10,000,000 stroops)
+        percentage_fee: 50,          // 0.5% = 50 basis points
+        min_fee: 500000,            // 0.05 XLM minimum
+        max_fee: 100000000,         // 10 XLM maximum
+    }
+}
+
+/// Get the fee structure configured by the admin
+fn fee_structure(env: &Env) -> FeeStructure {
+    env.storage()
+        .instance()
+        .get(&DataKey::FeeStructure)
+        .unwrap_or_else(default_fee_structure)
+}
+
+/// Convert a stroop-denominated amount to a token with `decimals` decimals
+fn scale_to_decimals(value: i128, decimals: u32) -> i128 {
+    if decimals >= FEE_DECIMALS {
+        value * 10i128.pow(decimals - FEE_DECIMALS)
+    } else {
+        value / 10i128.pow(FEE_DECIMALS - decimals)
+    }
+}
+
+/// Get the fee structure for a token, in the token's own units
+/// Tokens without their own schedule use the global one scaled to their decimals
+fn token_fee_structure(env: &Env, token: &TokenInfo) -> FeeStructure {
+    if let Some(custom) = env.storage()
+        .persistent()
+        .get(&PersistentDataKey::TokenFeeStructure(token.address.clone()))
+    {
+        return custom;
+    }
+
+    let global = fee_structure(env);
+    FeeStructure {
+        base_fee: scale_to_decimals(global.base_fee, token.decimals),
+        percentage_fee: global.percentage_fee,
+        min_fee: scale_to_decimals(global.min_fee, token.decimals),
+        max_fee: scale_to_decimals(global.max_fee, token.decimals),
+    }
+}
+
+/// Check admin-supplied fee parameters
+fn validate_fee_structure(fee_structure: &FeeStructure) -> Result<(), SaveXError> {
+    if fee_structure.base_fee < 0
+        || fee_structure.min_fee < 0
+        || fee_structure.max_fee < fee_structure.min_fee
+        || fee_structure.percentage_fee > 10000
+    {
+        return Err(SaveXError::InvalidFeeStructure);
+    }
+    Ok(())
+}
+
 /// Calculate basic fee (without discounts)
 fn calculate_base_fee(amount: i128, fee_structure: &FeeStructure) -> i128 {
     // Calculate percentage fee
@@ -143,698 +752,2381 @@ fn calculate_base_fee(amount: i128, fee_structure: &FeeStructure) -> i128 {
     }
 }
 
-// ============================================================================
-// Contract Implementation
-// ============================================================================
+/// Compute the fee breakdown for a transfer of `amount` of `token` sent by `from`
+fn compute_fee(
+    env: &Env,
+    from: &Address,
+    token: &TokenInfo,
+    amount: i128,
+    is_batch: bool,
+    batch_size: u32,
+) -> FeeBreakdown {
+    let fee_structure = token_fee_structure(env, token);
+
+    let base_fee = calculate_base_fee(amount, &fee_structure);
+
+    // Apply batch discount (10% per additional transfer)
+    let batch_discount = if is_batch && batch_size > 1 {
+        let discount_percentage = (batch_size - 1) * 10; // 10% per extra transfer
+        let max_discount = 50; // Max 50% discount
+        let actual_discount = if discount_percentage > max_discount {
+            max_discount
+        } else {
+            discount_percentage
+        };
+        (base_fee * actual_discount as i128) / 100
+    } else {
+        0
+    };
 
-#[contractimpl]
-impl SaveXContract {
+    // Apply package discount
+    let package_discount = (base_fee * package_discount(env, from, amount) as i128) / 10000;
 
-    // ========================================================================
-    // Initialization & Admin
-    // ========================================================================
+    let total_discount = (batch_discount + package_discount).min(base_fee);
+    let total_fee = base_fee - total_discount;
+    let final_fee = if total_fee < fee_structure.min_fee {
+        fee_structure.min_fee
+    } else {
+        total_fee
+    };
 
-    /// Initialize the contract with admin address
-    pub fn initialize(env: Env, admin: Address) {
-        if is_initialized(&env) {
-            panic!("Already initialized");
-        }
+    FeeBreakdown {
+        network_fee: base_fee / 2,      // Simulate network fee (half of base)
+        service_fee: base_fee / 2,      // Simulate service fee (half of base)
+        discount: total_discount,
+        total: final_fee,
+    }
+}
 
-        admin.require_auth();
+/// Charge the transfer fee on `amount`, sending it to the treasury
+/// Returns the fee taken (0 while no treasury is configured)
+fn collect_fee(
+    env: &Env,
+    payer: &Address,
+    token: &TokenInfo,
+    amount: i128,
+    is_batch: bool,
+    batch_size: u32,
+) -> Result<i128, SaveXError> {
+    collect_fee_from(env, payer, payer, token, amount, is_batch, batch_size)
+}
 
-        // Set admin
-        env.storage().instance().set(&DataKey::Admin, &admin);
+/// Charge the fee `payer` owes on `amount`, taking the tokens from `source`
+/// Used when the fee comes out of funds already escrowed in the contract
+fn collect_fee_from(
+    env: &Env,
+    payer: &Address,
+    source: &Address,
+    token: &TokenInfo,
+    amount: i128,
+    is_batch: bool,
+    batch_size: u32,
+) -> Result<i128, SaveXError> {
+    let treasury = match treasury_address(env) {
+        Ok(treasury) => treasury,
+        Err(_) => return Ok(0),
+    };
+
+    let fee = compute_fee(env, payer, token, amount, is_batch, batch_size).total;
+
+    if fee >= amount {
+        return Err(SaveXError::FeeExceedsAmount);
+    }
 
-        // Initialize counters
-        env.storage().instance().set(&DataKey::TransferCounter, &0u64);
-        env.storage().instance().set(&DataKey::RateLockCounter, &0u64);
-        env.storage().instance().set(&DataKey::PackageCounter, &0u64);
+    if fee > 0 {
+        token::Client::new(env, &token.address).transfer(source, &treasury, &fee);
+    }
 
-        // Set default unpausedt
-        env.storage().instance().set(&DataKey::IsPaused, &false);
+    Ok(fee)
+}
+
+/// Store the fee receipt for a transfer and publish `FeeCharged`
+fn record_fee_receipt(
+    env: &Env,
+    transfer_id: u64,
+    payer: &Address,
+    token: &Address,
+    gross_amount: i128,
+    fee: i128,
+) {
+    let receipt = FeeReceipt {
+        transfer_id,
+        payer: payer.clone(),
+        token: token.clone(),
+        gross_amount,
+        fee,
+        net_amount: gross_amount - fee,
+        charged_at: env.ledger().timestamp(),
+    };
+
+    let key = PersistentDataKey::FeeReceipt(transfer_id);
+    env.storage().persistent().set(&key, &receipt);
+    env.storage().persistent().extend_ttl(&key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+
+    if fee > 0 {
+        events::FeeCharged {
+            transfer_id,
+            payer: payer.clone(),
+            token: token.clone(),
+            fee,
+        }
+        .publish(env);
     }
+}
+
+/// Load a package subscription
+fn load_package(env: &Env, owner: &Address) -> Result<Package, SaveXError> {
+    env.storage()
+        .persistent()
+        .get(&PersistentDataKey::Package(owner.clone()))
+        .ok_or(SaveXError::PackageNotFound)
+}
+
+/// Helper: Store package in persistent storage with TTL
+fn store_package(env: &Env, package: &Package) {
+    let key = PersistentDataKey::Package(package.owner.clone());
+    env.storage().persistent().set(&key, package);
+    env.storage().persistent().extend_ttl(&key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+/// Check a package is active and has not reached its end date
+/// The package covers up to, but not including, `end_date`
+fn is_package_current(env: &Env, package: &Package) -> bool {
+    package.is_active && env.ledger().timestamp() < package.end_date
+}
+
+/// Default tier of a package type: flat discount, no quotas and a few seats
+fn default_package_tier(env: &Env, package_type: &PackageType) -> PackageTier {
+    let (discount_rate, seat_limit) = match package_type {
+        PackageType::Family => (1500, 5),       // 15% = 1500 basis points
+        PackageType::Business => (2000, 20),    // 20% = 2000 basis points
+        PackageType::Premium => (2500, 0),      // 25% = 2500 basis points
+    };
+
+    PackageTier {
+        discount_rate,
+        monthly_transfer_quota: 0,
+        monthly_volume_quota: 0,
+        discount_steps: Vec::new(env),
+        seat_limit,
+    }
+}
+
+/// Get the tier configured by the admin for a package type
+fn package_tier(env: &Env, package_type: &PackageType) -> PackageTier {
+    env.storage()
+        .instance()
+        .get(&DataKey::PackageTier(package_type.clone()))
+        .unwrap_or_else(|| default_package_tier(env, package_type))
+}
+
+/// Check admin-supplied tier parameters
+/// The tier discount plus its largest step may not exceed 100%
+fn validate_package_tier(tier: &PackageTier) -> Result<(), SaveXError> {
+    if tier.discount_rate > 10000
+        || tier.monthly_volume_quota < 0
+        || tier.discount_steps.len() > MAX_DISCOUNT_STEPS
+    {
+        return Err(SaveXError::InvalidPackageTier);
+    }
+
+    for step in tier.discount_steps.iter() {
+        if tier.discount_rate + step.extra_discount > 10000 {
+            return Err(SaveXError::InvalidPackageTier);
+        }
+    }
+
+    Ok(())
+}
+
+/// Start a new monthly usage period once the current one has ended
+fn roll_package_period(env: &Env, package: &mut Package) {
+    let current_time = env.ledger().timestamp();
+    if current_time >= package.period_start + PACKAGE_PERIOD {
+        let elapsed_periods = (current_time - package.period_start) / PACKAGE_PERIOD;
+        package.period_start += elapsed_periods * PACKAGE_PERIOD;
+        package.period_transfer_count = 0;
+        package.period_volume = 0;
+    }
+}
+
+/// Check a transfer of `amount` still fits in the period quotas of the tier
+fn is_within_quota(tier: &PackageTier, package: &Package, amount: i128) -> bool {
+    let within_count = tier.monthly_transfer_quota == 0
+        || package.period_transfer_count < tier.monthly_transfer_quota;
+    let within_volume = tier.monthly_volume_quota == 0
+        || package.period_volume + amount <= tier.monthly_volume_quota;
+
+    within_count && within_volume
+}
+
+/// Get the members added to an owner's package
+fn package_members(env: &Env, owner: &Address) -> Vec<Address> {
+    env.storage()
+        .persistent()
+        .get(&PersistentDataKey::PackageMembers(owner.clone()))
+        .unwrap_or_else(|| Vec::new(env))
+}
+
+/// Helper: Store package members in persistent storage with TTL
+fn store_package_members(env: &Env, owner: &Address, members: &Vec<Address>) {
+    let key = PersistentDataKey::PackageMembers(owner.clone());
+    env.storage().persistent().set(&key, members);
+    env.storage().persistent().extend_ttl(&key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+/// Helper: Unlink `member` from the owner's package and publish `PackageMemberRemoved`
+fn unlink_package_member(env: &Env, owner: &Address, member: &Address) -> Result<(), SaveXError> {
+    let mut members = package_members(env, owner);
+    let index = members.first_index_of(member).ok_or(SaveXError::MemberNotFound)?;
+
+    members.remove(index);
+    store_package_members(env, owner, &members);
+    env.storage().persistent().remove(&PersistentDataKey::PackageMember(member.clone()));
+
+    events::PackageMemberRemoved { owner: owner.clone(), member: member.clone() }.publish(env);
+
+    Ok(())
+}
+
+/// Helper: Unlink every member of the owner's package
+fn clear_package_members(env: &Env, owner: &Address) {
+    for member in package_members(env, owner).iter() {
+        env.storage().persistent().remove(&PersistentDataKey::PackageMember(member.clone()));
+        events::PackageMemberRemoved { owner: owner.clone(), member }.publish(env);
+    }
+    env.storage().persistent().remove(&PersistentDataKey::PackageMembers(owner.clone()));
+}
+
+/// Find the current package that covers `user`
+/// An own package takes precedence over one the user is a member of
+fn resolve_package(env: &Env, user: &Address) -> Option<Package> {
+    if let Ok(package) = load_package(env, user) {
+        if is_package_current(env, &package) {
+            return Some(package);
+        }
+    }
+
+    let owner: Address = env.storage()
+        .persistent()
+        .get(&PersistentDataKey::PackageMember(user.clone()))?;
+
+    match load_package(env, &owner) {
+        Ok(package) if is_package_current(env, &package) => Some(package),
+        _ => None,
+    }
+}
+
+/// Package discount in basis points for a transfer of `amount` by `sender`
+/// Transfers over the period quota get no package discount
+fn package_discount(env: &Env, sender: &Address, amount: i128) -> u32 {
+    let mut package = match resolve_package(env, sender) {
+        Some(package) => package,
+        None => return 0,
+    };
+    roll_package_period(env, &mut package);
+
+    let tier = package_tier(env, &package.package_type);
+    if !is_within_quota(&tier, &package, amount) {
+        return 0;
+    }
+
+    // Best step reached by the transfers already made this period
+    let mut extra_discount = 0;
+    for step in tier.discount_steps.iter() {
+        if package.period_transfer_count >= step.min_transfers && step.extra_discount > extra_discount {
+            extra_discount = step.extra_discount;
+        }
+    }
+
+    tier.discount_rate + extra_discount
+}
+
+/// Order of package tiers, used to tell upgrades from downgrades
+fn package_rank(package_type: &PackageType) -> u32 {
+    match package_type {
+        PackageType::Family => 1,
+        PackageType::Business => 2,
+        PackageType::Premium => 3,
+    }
+}
+
+/// Get the admin-set price of a package type
+fn package_price(env: &Env, package_type: &PackageType) -> Result<PackagePrice, SaveXError> {
+    env.storage()
+        .instance()
+        .get(&DataKey::PackagePrice(package_type.clone()))
+        .ok_or(SaveXError::PackagePriceNotSet)
+}
+
+/// Whole unused days of a package and their value at the package price
+/// The value is capped at the payment still held for the package
+fn unused_package_credit(env: &Env, package: &Package) -> (u64, i128) {
+    let current_time = env.ledger().timestamp();
+    let unused_days = if current_time < package.end_date {
+        (package.end_date - current_time) / 86400
+    } else {
+        0
+    };
+
+    let credit = (unused_days as i128 * package.price_per_day).min(package.prepaid);
+    (unused_days, credit)
+}
+
+/// Append a step to the owner's package history
+fn record_package_history(
+    env: &Env,
+    package: &Package,
+    action: PackageAction,
+    amount_paid: i128,
+    refund: i128,
+) {
+    let key = PersistentDataKey::PackageHistory(package.owner.clone());
+    let mut history: Vec<PackageHistoryEntry> = env.storage()
+        .persistent()
+        .get(&key)
+        .unwrap_or_else(|| Vec::new(env));
+
+    history.push_back(PackageHistoryEntry {
+        action,
+        package_type: package.package_type.clone(),
+        timestamp: env.ledger().timestamp(),
+        end_date: package.end_date,
+        amount_paid,
+        refund,
+    });
+
+    env.storage().persistent().set(&key, &history);
+    env.storage().persistent().extend_ttl(&key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+}
+
+/// Move an active package to another tier until its current end date
+/// The unused days of the old tier are credited against the new one
+fn change_package(
+    env: &Env,
+    owner: &Address,
+    new_type: PackageType,
+    action: PackageAction,
+) -> Result<(), SaveXError> {
+    require_not_paused(env)?;
+
+    owner.require_auth();
+
+    let mut package = load_package(env, owner)?;
+    if !is_package_current(env, &package) {
+        return Err(SaveXError::PackageNotActive);
+    }
+
+    let is_valid_change = if action == PackageAction::Upgraded {
+        package_rank(&new_type) > package_rank(&package.package_type)
+    } else {
+        package_rank(&new_type) < package_rank(&package.package_type)
+    };
+    if !is_valid_change {
+        return Err(SaveXError::InvalidPackageChange);
+    }
+
+    if package_members(env, owner).len() > package_tier(env, &new_type).seat_limit {
+        return Err(SaveXError::SeatLimitReached);
+    }
+
+    let price = package_price(env, &new_type)?;
+    let (unused_days, credit) = unused_package_credit(env, &package);
+    let new_cost = unused_days as i128 * price.price_per_day;
+
+    // The used part of the old tier is earned
+    release_package_revenue(env, &package, package.prepaid - credit)?;
+
+    // Credit can only offset the new cost when both are in the same token
+    let (amount_paid, refund) = if price.token == package.payment_token {
+        if new_cost >= credit {
+            (new_cost - credit, 0)
+        } else {
+            (0, credit - new_cost)
+        }
+    } else {
+        (new_cost, credit)
+    };
+
+    if refund > 0 {
+        token::Client::new(env, &package.payment_token).transfer(
+            &env.current_contract_address(),
+            owner,
+            &refund,
+        );
+    }
+    if amount_paid > 0 {
+        token::Client::new(env, &price.token).transfer(
+            owner,
+            env.current_contract_address(),
+            &amount_paid,
+        );
+    }
+
+    let from_type = package.package_type.clone();
+    package.package_type = new_type.clone();
+    package.discount_rate = package_tier(env, &new_type).discount_rate;
+    package.payment_token = price.token;
+    package.price_per_day = price.price_per_day;
+    package.prepaid = new_cost;
+
+    store_package(env, &package);
+    record_package_history(env, &package, action, amount_paid, refund);
+
+    events::PackageChanged {
+        owner: owner.clone(),
+        from_type,
+        to_type: new_type,
+        amount_paid,
+        refund,
+    }
+    .publish(env);
+
+    Ok(())
+}
+
+/// Count a transfer of `amount` towards the package covering the sender
+/// Publishes `PackageQuotaExceeded` when the transfer went over the quota
+fn record_package_usage(env: &Env, sender: &Address, amount: i128) {
+    let mut package = match resolve_package(env, sender) {
+        Some(package) => package,
+        None => return,
+    };
+    roll_package_period(env, &mut package);
+
+    let tier = package_tier(env, &package.package_type);
+    let within_quota = is_within_quota(&tier, &package, amount);
+
+    package.transfer_count += 1;
+    package.total_volume += amount;
+    package.period_transfer_count += 1;
+    package.period_volume += amount;
+    store_package(env, &package);
+
+    if !within_quota {
+        events::PackageQuotaExceeded {
+            owner: package.owner.clone(),
+            package_type: package.package_type,
+            period_transfer_count: package.period_transfer_count,
+            period_volume: package.period_volume,
+        }
+        .publish(env);
+    }
+}
+
+/// Send the earned part of a package payment to the treasury
+fn release_package_revenue(env: &Env, package: &Package, amount: i128) -> Result<(), SaveXError> {
+    if amount > 0 {
+        let treasury = treasury_address(env)?;
+        token::Client::new(env, &package.payment_token).transfer(
+            &env.current_contract_address(),
+            &treasury,
+            &amount,
+        );
+    }
+    Ok(())
+}
+
+// ============================================================================
+// Contract Implementation
+// ============================================================================
+
+#[contractimpl]
+impl SaveXContract {
+
+    // ========================================================================
+    // Initialization & Admin
+    // ========================================================================
+
+    /// Initialize the contract with admin address
+    pub fn initialize(env: Env, admin: Address) -> Result<(), SaveXError> {
+        // An instance configured as an ArbitrageBot can't become SaveX too
+        if is_initialized(&env) || env.storage().instance().has(&ArbitrageDataKey::Config) {
+            return Err(SaveXError::AlreadyInitialized);
+        }
 
-    /// Pause the contract (admin only)
-    pub fn pause(env: Env, admin: Address) {
         admin.require_auth();
 
-        let stored_admin: Address = env.storage()
-            .instance()
-            .get(&DataKey::Admin)
-            .expect("Not initialized");
+        // Set admin
+        env.storage().instance().set(&DataKey::Admin, &admin);
+
+        // Initialize counters
+        env.storage().instance().set(&DataKey::TransferCounter, &0u64);
+        env.storage().instance().set(&DataKey::RateLockCounter, &0u64);
+        env.storage().instance().set(&DataKey::RecurringCounter, &0u64);
+        env.storage().instance().set(&DataKey::BatchCounter, &0u64);
+        env.storage().instance().set(&DataKey::PackageCounter, &0u64);
+
+        // Set default unpaused
+        env.storage().instance().set(&DataKey::IsPaused, &false);
+
+        events::Initialized { admin }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Pause the contract (admin only)
+    pub fn pause(env: Env, admin: Address) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+
+        env.storage().instance().set(&DataKey::IsPaused, &true);
+
+        events::Paused { admin }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Unpause the contract (admin only)
+    pub fn unpause(env: Env, admin: Address) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+
+        env.storage().instance().set(&DataKey::IsPaused, &false);
+
+        events::Unpaused { admin }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Set Soroswap Router address for swap functionality (admin only)
+    pub fn set_router_address(env: Env, admin: Address, router: Address) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+
+        env.storage().instance().set(&DataKey::RouterAddress, &router);
+
+        events::RouterUpdated { admin, router }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Get configured Soroswap Router address
+    pub fn get_router_address(env: Env) -> Result<Address, SaveXError> {
+        router_address(&env)
+    }
+
+    /// Set Soroswap Factory address for pool queries (admin only)
+    pub fn set_factory_address(env: Env, admin: Address, factory: Address) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+
+        env.storage().instance().set(&DataKey::FactoryAddress, &factory);
+
+        events::FactoryUpdated { admin, factory }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Get configured Soroswap Factory address
+    pub fn get_factory_address(env: Env) -> Result<Address, SaveXError> {
+        factory_address(&env)
+    }
+
+    /// Set the fee parameters charged on transfers (admin only)
+    pub fn set_fee_structure(env: Env, admin: Address, fee_structure: FeeStructure) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+        validate_fee_structure(&fee_structure)?;
+
+        env.storage().instance().set(&DataKey::FeeStructure, &fee_structure);
+
+        events::FeeStructureUpdated { admin, fee_structure }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Get the fee parameters charged on transfers
+    pub fn get_fee_structure(env: Env) -> FeeStructure {
+        fee_structure(&env)
+    }
+
+    /// Set the treasury that receives collected fees (admin only)
+    /// Fees are only collected once a treasury is configured
+    pub fn set_treasury(env: Env, admin: Address, treasury: Address) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+
+        env.storage().instance().set(&DataKey::Treasury, &treasury);
+
+        events::TreasuryUpdated { admin, treasury }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Get the configured treasury address
+    pub fn get_treasury(env: Env) -> Result<Address, SaveXError> {
+        treasury_address(&env)
+    }
+
+    // ========================================================================
+    // Token Registry
+    // ========================================================================
+
+    /// Register a token (or update its metadata) and mark it supported (admin only)
+    pub fn register_token(
+        env: Env,
+        admin: Address,
+        token: Address,
+        symbol: String,
+        decimals: u32,
+    ) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+
+        if decimals > MAX_TOKEN_DECIMALS {
+            return Err(SaveXError::InvalidDecimals);
+        }
+
+        let info = TokenInfo {
+            address: token.clone(),
+            symbol: symbol.clone(),
+            decimals,
+            is_supported: true,
+        };
+        store_token(&env, &info);
+
+        events::TokenRegistered { admin, token, symbol, decimals }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Enable or disable transfers in a registered token (admin only)
+    pub fn set_token_supported(
+        env: Env,
+        admin: Address,
+        token: Address,
+        is_supported: bool,
+    ) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+
+        let mut info = load_token(&env, &token)?;
+        info.is_supported = is_supported;
+        store_token(&env, &info);
+
+        events::TokenSupportUpdated { admin, token, is_supported }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Set the fee parameters of a registered token, in its own units (admin only)
+    pub fn set_token_fee_structure(
+        env: Env,
+        admin: Address,
+        token: Address,
+        fee_structure: FeeStructure,
+    ) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+        load_token(&env, &token)?;
+        validate_fee_structure(&fee_structure)?;
+
+        let key = PersistentDataKey::TokenFeeStructure(token.clone());
+        env.storage().persistent().set(&key, &fee_structure);
+        env.storage().persistent().extend_ttl(&key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+
+        events::TokenFeeStructureUpdated { admin, token, fee_structure }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Get the registry entry of a token
+    pub fn get_token_info(env: Env, token: Address) -> Result<TokenInfo, SaveXError> {
+        load_token(&env, &token)
+    }
+
+    /// Get the fee parameters applied to a token, in its own units
+    pub fn get_token_fee_structure(env: Env, token: Address) -> Result<FeeStructure, SaveXError> {
+        let info = load_token(&env, &token)?;
+        Ok(token_fee_structure(&env, &info))
+    }
+
+    // ========================================================================
+    // Rate Locking Module
+    // ========================================================================
+
+    /// Lock the current Soroswap rate for `amount` of `from_token`
+    /// The owner escrows `amount` plus a premium that grows with the lock
+    /// duration; the premium goes to the hedge reserve and is not refunded
+    /// Fails with `InvalidRate` if the pool quotes nothing or the admin has not
+    /// set a reference rate for the pair, and with `SlippageExceeded` if the
+    /// pool rate is more than 2% off the reference rate
+    pub fn lock_rate(
+        env: Env,
+        owner: Address,
+        from_token: Address,
+        to_token: Address,
+        amount: i128,
+        duration_seconds: u64,
+    ) -> Result<u64, SaveXError> {
+        require_not_paused(&env)?;
+
+        owner.require_auth();
+
+        if amount <= 0 {
+            return Err(SaveXError::InvalidAmount);
+        }
+
+        // Maximum lock duration: 24 hours
+        if duration_seconds > 86400 {
+            return Err(SaveXError::DurationTooLong);
+        }
+
+        require_supported_token(&env, &from_token)?;
+        require_supported_token(&env, &to_token)?;
+
+        // Price the lock from the live pool
+        let quote = soroswap_quote(&env, &from_token, &to_token, amount)?;
+        let locked_rate = (quote * RATE_PRECISION) / amount;
+        if locked_rate <= 0 {
+            return Err(SaveXError::InvalidRate);
+        }
+
+        require_near_reference_rate(&env, &from_token, &to_token, locked_rate)?;
+
+        let premium = lock_premium(amount, duration_seconds);
+        token::Client::new(&env, &from_token).transfer(
+            &owner,
+            env.current_contract_address(),
+            &(amount + premium)
+        );
+        if premium > 0 {
+            update_hedge_reserve(&env, &from_token, premium)?;
+        }
+
+        let current_time = env.ledger().timestamp();
+        let expiry = current_time + duration_seconds;
+        let id = next_rate_lock_id(&env);
+
+        let rate_lock = RateLock {
+            id,
+            owner: owner.clone(),
+            from_token: from_token.clone(),
+            to_token: to_token.clone(),
+            locked_rate,
+            amount,
+            expiry,
+            is_active: true,
+            created_at: current_time,
+            remaining_amount: amount,
+            premium,
+            fills: Vec::new(&env),
+        };
+
+        store_rate_lock(&env, &rate_lock);
+        push_index(&env, UserIndex::RateLocks(rate_lock.owner.clone()), id);
+
+        events::RateLockCreated {
+            id,
+            owner,
+            from_token,
+            to_token,
+            locked_rate,
+            amount,
+            premium,
+            expiry,
+        }
+        .publish(&env);
+
+        Ok(id)
+    }
+
+    /// Get rate lock details
+    pub fn get_rate_lock(env: Env, lock_id: u64) -> Result<RateLock, SaveXError> {
+        load_rate_lock(&env, lock_id)
+    }
+
+    /// Get the premium charged for locking `amount` for `duration_seconds`
+    pub fn get_lock_premium(_env: Env, amount: i128, duration_seconds: u64) -> i128 {
+        lock_premium(amount, duration_seconds)
+    }
+
+    /// Cancel a rate lock and refund its unused escrow
+    pub fn cancel_rate_lock(env: Env, owner: Address, lock_id: u64) -> Result<(), SaveXError> {
+        owner.require_auth();
+
+        let mut rate_lock = load_rate_lock(&env, lock_id)?;
+
+        if rate_lock.owner != owner {
+            return Err(SaveXError::NotAuthorized);
+        }
+
+        if !rate_lock.is_active {
+            return Err(SaveXError::RateLockNotActive);
+        }
+
+        let refund = close_rate_lock(&env, &mut rate_lock);
+
+        events::RateLockCancelled { id: lock_id, owner, refund }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Close an expired rate lock and refund its unused escrow to the owner
+    /// Anyone can call this once the lock has expired
+    pub fn release_expired_rate_lock(env: Env, lock_id: u64) -> Result<(), SaveXError> {
+        let mut rate_lock = load_rate_lock(&env, lock_id)?;
+
+        if !rate_lock.is_active {
+            return Err(SaveXError::RateLockNotActive);
+        }
+
+        if env.ledger().timestamp() <= rate_lock.expiry {
+            return Err(SaveXError::RateLockNotExpired);
+        }
+
+        let refund = close_rate_lock(&env, &mut rate_lock);
+
+        events::RateLockExpired {
+            id: lock_id,
+            owner: rate_lock.owner,
+            refund,
+            keeper_reward: 0,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    /// Get rate locks created by an address, oldest first
+    /// `cursor` is the position to start from; at most 50 locks are returned
+    /// Closed locks whose storage has expired are skipped
+    pub fn get_rate_locks_by_owner(env: Env, owner: Address, cursor: u64, limit: u32) -> Vec<RateLock> {
+        let mut rate_locks = Vec::new(&env);
+        for id in read_index(&env, UserIndex::RateLocks(owner), cursor, limit).iter() {
+            if let Ok(rate_lock) = load_rate_lock(&env, id) {
+                rate_locks.push_back(rate_lock);
+            }
+        }
+        rate_locks
+    }
+
+    /// Close every expired lock in `lock_ids` and refund its escrow to the owner
+    /// Anyone can call this; the keeper earns a small share of each swept escrow
+    /// Unknown, inactive or unexpired locks are skipped
+    /// Returns the number of locks swept
+    pub fn sweep_expired_locks(
+        env: Env,
+        keeper: Address,
+        lock_ids: Vec<u64>,
+    ) -> Result<u32, SaveXError> {
+        if lock_ids.len() > MAX_SWEEP_SIZE {
+            return Err(SaveXError::BatchTooLarge);
+        }
+
+        let now = env.ledger().timestamp();
+        let mut swept = 0u32;
+
+        for lock_id in lock_ids.iter() {
+            let mut rate_lock = match load_rate_lock(&env, lock_id) {
+                Ok(rate_lock) => rate_lock,
+                Err(_) => continue,
+            };
+
+            if !rate_lock.is_active || now <= rate_lock.expiry {
+                continue;
+            }
+
+            let keeper_reward = rate_lock.remaining_amount * KEEPER_REWARD_BPS / 10000;
+            if keeper_reward > 0 {
+                token::Client::new(&env, &rate_lock.from_token).transfer(
+                    &env.current_contract_address(),
+                    &keeper,
+                    &keeper_reward,
+                );
+                rate_lock.remaining_amount -= keeper_reward;
+            }
+
+            let refund = close_rate_lock(&env, &mut rate_lock);
+
+            events::RateLockExpired {
+                id: lock_id,
+                owner: rate_lock.owner,
+                refund,
+                keeper_reward,
+            }
+            .publish(&env);
+
+            swept += 1;
+        }
+
+        Ok(swept)
+    }
+
+    // ========================================================================
+    // Transfer Module
+    // ========================================================================
+
+    /// Create an immediate transfer
+    /// The transfer fee is deducted from `amount` and sent to the treasury
+    pub fn transfer_immediate(
+        env: Env,
+        from: Address,
+        to: Address,
+        token: Address,
+        amount: i128,
+    ) -> Result<u64, SaveXError> {
+        require_not_paused(&env)?;
+
+        from.require_auth();
+
+        if amount <= 0 {
+            return Err(SaveXError::InvalidAmount);
+        }
+
+        let token_info = require_supported_token(&env, &token)?;
+        let fee = collect_fee(&env, &from, &token_info, amount, false, 1)?;
+        let net_amount = amount - fee;
+        record_package_usage(&env, &from, amount);
+
+        // Execute transfer immediately
+        token::Client::new(&env, &token).transfer(&from, &to, &net_amount);
+
+        // Store transfer record
+        let transfer = new_transfer(&env, &from, &to, &token, net_amount, TransferType::Immediate, 0);
+        save_new_transfer(&env, &transfer);
+        record_fee_receipt(&env, transfer.id, &from, &token, amount, fee);
+
+        Ok(transfer.id)
+    }
+
+    /// Create a scheduled (time-locked) transfer
+    /// The fee is charged up front; only the net amount is escrowed
+    pub fn transfer_scheduled(
+        env: Env,
+        from: Address,
+        to: Address,
+        token: Address,
+        amount: i128,
+        execute_after: u64,
+    ) -> Result<u64, SaveXError> {
+        require_not_paused(&env)?;
+
+        from.require_auth();
+
+        if execute_after <= env.ledger().timestamp() {
+            return Err(SaveXError::InvalidTimestamp);
+        }
+
+        let (net_amount, fee) = escrow_transfer_amount(&env, &from, &token, amount)?;
+
+        // Store transfer record
+        let transfer = new_transfer(&env, &from, &to, &token, net_amount, TransferType::Scheduled, execute_after);
+        save_new_transfer(&env, &transfer);
+        record_fee_receipt(&env, transfer.id, &from, &token, amount, fee);
+
+        Ok(transfer.id)
+    }
+
+    /// Create a transfer the recipient pulls with a claim code
+    /// `claim_hash` is the SHA-256 of the claim code shared with the recipient
+    /// Unclaimed funds can be reclaimed by the sender after `claim_deadline`
+    pub fn transfer_claimable(
+        env: Env,
+        from: Address,
+        token: Address,
+        amount: i128,
+        claim_hash: BytesN<32>,
+        claim_deadline: u64,
+    ) -> Result<u64, SaveXError> {
+        require_not_paused(&env)?;
+
+        from.require_auth();
+
+        if claim_deadline <= env.ledger().timestamp() {
+            return Err(SaveXError::InvalidTimestamp);
+        }
+
+        let (net_amount, fee) = escrow_transfer_amount(&env, &from, &token, amount)?;
+
+        let mut transfer = new_transfer(&env, &from, &from, &token, net_amount, TransferType::Claimable, 0);
+        transfer.has_time_bound = true;
+        transfer.time_bound_kind = TimeBoundKind::Before;
+        transfer.time_bound_timestamp = claim_deadline;
+        transfer.status = TransferStatus::Claimable;
+        transfer.claim_hash = claim_hash;
+        save_new_transfer(&env, &transfer);
+        record_fee_receipt(&env, transfer.id, &from, &token, amount, fee);
+
+        Ok(transfer.id)
+    }
+
+    /// Claim a claimable transfer by presenting its claim code
+    /// Anyone holding the claim code can pay the funds out to `destination`
+    pub fn claim_transfer(
+        env: Env,
+        transfer_id: u64,
+        claim_code: Bytes,
+        destination: Address,
+    ) -> Result<(), SaveXError> {
+        require_not_paused(&env)?;
+
+        let mut transfer = load_transfer(&env, transfer_id)?;
+
+        if transfer.status != TransferStatus::Claimable {
+            return Err(SaveXError::TransferNotPending);
+        }
+
+        let deadline = TimeBound {
+            kind: transfer.time_bound_kind.clone(),
+            timestamp: transfer.time_bound_timestamp,
+        };
+
+        if !check_time_bound(&env, &deadline) {
+            return Err(SaveXError::ClaimExpired);
+        }
+
+        if BytesN::from(env.crypto().sha256(&claim_code)) != transfer.claim_hash {
+            return Err(SaveXError::InvalidClaimCode);
+        }
+
+        token::Client::new(&env, &transfer.token).transfer(
+            &env.current_contract_address(),
+            &destination,
+            &transfer.amount
+        );
+
+        transfer.to = destination;
+        transfer.status = TransferStatus::Claimed;
+        store_transfer(&env, &transfer);
+        push_index(&env, UserIndex::Received(transfer.to.clone()), transfer.id);
+        events::transfer_executed(&env, &transfer);
+
+        Ok(())
+    }
+
+    /// Return an unclaimed transfer to its sender once the claim deadline has passed
+    pub fn reclaim_transfer(env: Env, owner: Address, transfer_id: u64) -> Result<(), SaveXError> {
+        owner.require_auth();
+
+        let mut transfer = load_transfer(&env, transfer_id)?;
+
+        if transfer.from != owner {
+            return Err(SaveXError::NotAuthorized);
+        }
+
+        if transfer.status != TransferStatus::Claimable {
+            return Err(SaveXError::TransferNotPending);
+        }
+
+        if env.ledger().timestamp() <= transfer.time_bound_timestamp {
+            return Err(SaveXError::ClaimNotExpired);
+        }
+
+        token::Client::new(&env, &transfer.token).transfer(
+            &env.current_contract_address(),
+            &transfer.from,
+            &transfer.amount
+        );
+
+        transfer.status = TransferStatus::Cancelled;
+        store_transfer(&env, &transfer);
+        events::transfer_cancelled(&env, &transfer);
+
+        Ok(())
+    }
+
+    /// Execute a scheduled transfer (anyone can call, but only after time)
+    pub fn execute_scheduled_transfer(env: Env, transfer_id: u64) -> Result<(), SaveXError> {
+        require_not_paused(&env)?;
+
+        let mut transfer = load_transfer(&env, transfer_id)?;
+
+        if transfer.status != TransferStatus::Locked {
+            return Err(SaveXError::TransferNotPending);
+        }
+
+        // Escrows and streams are locked too, but settle through their own calls
+        if !is_scheduled_leg(&transfer) {
+            return Err(SaveXError::InvalidTimebound);
+        }
+
+        let time_bound = TimeBound {
+            kind: transfer.time_bound_kind.clone(),
+            timestamp: transfer.time_bound_timestamp,
+        };
+
+        if !check_time_bound(&env, &time_bound) {
+            return Err(SaveXError::TimeBoundNotMet);
+        }
+
+        settle_locked_transfer(&env, &mut transfer, true);
+
+        Ok(())
+    }
+
+    /// Cancel a scheduled transfer (owner only, before execution)
+    pub fn cancel_scheduled_transfer(env: Env, owner: Address, transfer_id: u64) -> Result<(), SaveXError> {
+        owner.require_auth();
+
+        let mut transfer = load_transfer(&env, transfer_id)?;
+
+        if transfer.from != owner {
+            return Err(SaveXError::NotAuthorized);
+        }
+
+        if transfer.status != TransferStatus::Locked {
+            return Err(SaveXError::TransferNotPending);
+        }
+
+        if !is_scheduled_leg(&transfer) {
+            return Err(SaveXError::InvalidTimebound);
+        }
+
+        settle_locked_transfer(&env, &mut transfer, false);
+
+        Ok(())
+    }
+
+    /// Get transfer details
+    pub fn get_transfer(env: Env, transfer_id: u64) -> Result<Transfer, SaveXError> {
+        load_transfer(&env, transfer_id)
+    }
+
+    /// Get transfers sent by an address, oldest first
+    /// `cursor` is the position to start from; at most 50 transfers are returned
+    pub fn get_transfers_by_sender(env: Env, sender: Address, cursor: u64, limit: u32) -> Vec<Transfer> {
+        let ids = read_index(&env, UserIndex::Sent(sender), cursor, limit);
+        load_transfers(&env, ids)
+    }
+
+    /// Get transfers received by an address, oldest first
+    /// `cursor` is the position to start from; at most 50 transfers are returned
+    pub fn get_transfers_by_recipient(env: Env, recipient: Address, cursor: u64, limit: u32) -> Vec<Transfer> {
+        let ids = read_index(&env, UserIndex::Received(recipient), cursor, limit);
+        load_transfers(&env, ids)
+    }
+
+    /// Get transfers an address sends or receives that are still waiting to settle
+    /// `cursor` is the position to start from and at most 50 positions are read;
+    /// transfers settled since are skipped, so a page may hold fewer than `limit`
+    pub fn get_pending_transfers(env: Env, user: Address, cursor: u64, limit: u32) -> Vec<Transfer> {
+        let ids = read_index(&env, UserIndex::Pending(user), cursor, limit);
+        let mut pending = Vec::new(&env);
+        for transfer in load_transfers(&env, ids).iter() {
+            if is_unsettled(&transfer) {
+                pending.push_back(transfer);
+            }
+        }
+        pending
+    }
+
+    /// Get the fee receipt of a transfer
+    pub fn get_fee_receipt(env: Env, transfer_id: u64) -> Result<FeeReceipt, SaveXError> {
+        env.storage()
+            .persistent()
+            .get(&PersistentDataKey::FeeReceipt(transfer_id))
+            .ok_or(SaveXError::FeeReceiptNotFound)
+    }
+
+    // ========================================================================
+    // Transfer with Rate Lock
+    // ========================================================================
+
+    /// Create a transfer using a locked rate
+    /// Swaps `amount` of the lock's escrowed `from_token` through the router
+    /// and delivers exactly `amount * locked_rate / 1e7` of its `to_token`
+    /// The hedge reserve covers any shortfall and keeps any surplus, but only for
+    /// pool rates within 2% of the pair's reference rate (`SlippageExceeded`
+    /// otherwise, `InvalidRate` if no reference rate is set); a shortfall the
+    /// reserve can't cover is `InsufficientHedgeReserve`
+    /// Each transfer uses up part of the lock until it is exhausted
+    pub fn transfer_with_rate_lock(
+        env: Env,
+        from: Address,
+        to: Address,
+        amount: i128,
+        rate_lock_id: u64,
+    ) -> Result<u64, SaveXError> {
+        require_not_paused(&env)?;
+
+        from.require_auth();
+
+        if amount <= 0 {
+            return Err(SaveXError::InvalidAmount);
+        }
+
+        // Validate rate lock
+        let mut rate_lock = load_rate_lock(&env, rate_lock_id)?;
+
+        if rate_lock.owner != from {
+            return Err(SaveXError::NotAuthorized);
+        }
+
+        if !rate_lock.is_active {
+            return Err(SaveXError::RateLockNotActive);
+        }
+
+        let current_time = env.ledger().timestamp();
+        if current_time > rate_lock.expiry {
+            return Err(SaveXError::RateLockExpired);
+        }
+
+        if amount > rate_lock.remaining_amount {
+            return Err(SaveXError::AmountExceedsLock);
+        }
+
+        require_supported_token(&env, &rate_lock.from_token)?;
+        require_supported_token(&env, &rate_lock.to_token)?;
+
+        let guaranteed_amount = (amount * rate_lock.locked_rate) / RATE_PRECISION;
+
+        // The reserve only absorbs drift around the reference rate
+        let quote = soroswap_quote(&env, &rate_lock.from_token, &rate_lock.to_token, amount)?;
+        require_near_reference_rate(
+            &env,
+            &rate_lock.from_token,
+            &rate_lock.to_token,
+            (quote * RATE_PRECISION) / amount,
+        )?;
+        if guaranteed_amount - quote > hedge_reserve(&env, &rate_lock.to_token) {
+            return Err(SaveXError::InsufficientHedgeReserve);
+        }
+
+        // Swap the escrow at the quoted pool rate
+        let output_amount = execute_swap(&env, &rate_lock.from_token, &rate_lock.to_token, amount, quote)?;
+
+        // Settle the difference to the locked rate against the hedge reserve
+        if output_amount != guaranteed_amount {
+            update_hedge_reserve(&env, &rate_lock.to_token, output_amount - guaranteed_amount)?;
+        }
+
+        token::Client::new(&env, &rate_lock.to_token).transfer(
+            &env.current_contract_address(),
+            &to,
+            &guaranteed_amount
+        );
+        record_package_usage(&env, &from, amount);
+
+        // Store transfer record (token is the final token received)
+        let mut transfer = new_transfer(
+            &env,
+            &from,
+            &to,
+            &rate_lock.to_token,
+            guaranteed_amount,
+            TransferType::Immediate,
+            0,
+        );
+        transfer.has_rate_lock = true;
+        transfer.rate_lock_id = rate_lock_id;
+        save_new_transfer(&env, &transfer);
+
+        // Record the fill and close the lock once it is used up
+        rate_lock.remaining_amount -= amount;
+        rate_lock.fills.push_back(transfer.id);
+        if rate_lock.remaining_amount == 0 {
+            rate_lock.is_active = false;
+        }
+        store_rate_lock(&env, &rate_lock);
+
+        events::RateLockUsed {
+            id: rate_lock_id,
+            owner: from,
+            transfer_id: transfer.id,
+            amount,
+            amount_out: guaranteed_amount,
+            remaining_amount: rate_lock.remaining_amount,
+        }
+        .publish(&env);
+
+        Ok(transfer.id)
+    }
+
+    /// Add tokens to the hedge reserve backing rate locks (admin only)
+    pub fn fund_hedge_reserve(env: Env, admin: Address, token: Address, amount: i128) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+
+        if amount <= 0 {
+            return Err(SaveXError::InvalidAmount);
+        }
+
+        token::Client::new(&env, &token).transfer(&admin, env.current_contract_address(), &amount);
+        update_hedge_reserve(&env, &token, amount)
+    }
+
+    /// Take tokens out of the hedge reserve (admin only)
+    pub fn withdraw_hedge_reserve(env: Env, admin: Address, token: Address, amount: i128) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+
+        if amount <= 0 {
+            return Err(SaveXError::InvalidAmount);
+        }
+
+        update_hedge_reserve(&env, &token, -amount)?;
+        token::Client::new(&env, &token).transfer(&env.current_contract_address(), &admin, &amount);
+
+        Ok(())
+    }
+
+    /// Get the hedge reserve held for a token
+    pub fn get_hedge_reserve(env: Env, token: Address) -> i128 {
+        hedge_reserve(&env, &token)
+    }
+
+    /// Set the reference rate (7 decimals) of a token pair (admin only)
+    /// Locks are only granted at pool rates within 2% of it
+    pub fn set_reference_rate(
+        env: Env,
+        admin: Address,
+        from_token: Address,
+        to_token: Address,
+        rate: i128,
+    ) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+
+        if rate <= 0 {
+            return Err(SaveXError::InvalidRate);
+        }
+
+        env.storage()
+            .instance()
+            .set(&DataKey::ReferenceRate(from_token.clone(), to_token.clone()), &rate);
+
+        events::ReferenceRateUpdated { from_token, to_token, rate }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Get the reference rate of a token pair, if set
+    pub fn get_reference_rate(env: Env, from_token: Address, to_token: Address) -> Option<i128> {
+        env.storage().instance().get(&DataKey::ReferenceRate(from_token, to_token))
+    }
+
+    // ========================================================================
+    // Split Transfer
+    // ========================================================================
+
+    /// Split transfer: Send part now, part later
+    /// The fee is charged once on `total_amount` and shared between both legs
+    /// Both legs are grouped in a batch record, see `Transfer::batch_id`
+    /// Returns tuple of (immediate_transfer_id, scheduled_transfer_id)
+    pub fn transfer_split(
+        env: Env,
+        from: Address,
+        to: Address,
+        token: Address,
+        total_amount: i128,
+        immediate_percentage: u32,  // 0-100, e.g., 50 = 50%
+        schedule_after: u64,
+    ) -> Result<(u64, u64), SaveXError> {
+        require_not_paused(&env)?;
+
+        from.require_auth();
+
+        if total_amount <= 0 {
+            return Err(SaveXError::InvalidAmount);
+        }
+
+        if immediate_percentage > 100 {
+            return Err(SaveXError::InvalidPercentage);
+        }
+
+        let current_time = env.ledger().timestamp();
+        if schedule_after <= current_time {
+            return Err(SaveXError::InvalidTimestamp);
+        }
+
+        let token_info = require_supported_token(&env, &token)?;
+        let fee = collect_fee(&env, &from, &token_info, total_amount, false, 1)?;
+        let net_total = total_amount - fee;
+        record_package_usage(&env, &from, total_amount);
+        let mut batch = new_batch(&env, &from);
+
+        // Calculate split amounts (fee is shared in the same proportion)
+        let immediate_amount = (net_total * immediate_percentage as i128) / 100;
+        let scheduled_amount = net_total - immediate_amount;
+        let immediate_fee = (fee * immediate_percentage as i128) / 100;
+        let scheduled_fee = fee - immediate_fee;
+
+        // Execute immediate transfer
+        let immediate_id = if immediate_amount > 0 {
+            token::Client::new(&env, &token).transfer(&from, &to, &immediate_amount);
+
+            let mut transfer = new_transfer(&env, &from, &to, &token, immediate_amount, TransferType::Split, 0);
+            save_batch_leg(&env, &mut batch, &mut transfer, immediate_amount + immediate_fee);
+            record_fee_receipt(&env, transfer.id, &from, &token, immediate_amount + immediate_fee, immediate_fee);
+            transfer.id
+        } else {
+            0
+        };
+
+        // Create scheduled transfer
+        let scheduled_id = if scheduled_amount > 0 {
+            // Lock tokens in contract
+            token::Client::new(&env, &token).transfer(
+                &from,
+                env.current_contract_address(),
+                &scheduled_amount
+            );
+
+            let mut transfer = new_transfer(&env, &from, &to, &token, scheduled_amount, TransferType::Split, schedule_after);
+            save_batch_leg(&env, &mut batch, &mut transfer, scheduled_amount + scheduled_fee);
+            record_fee_receipt(&env, transfer.id, &from, &token, scheduled_amount + scheduled_fee, scheduled_fee);
+            transfer.id
+        } else {
+            0
+        };
+
+        save_new_batch(&env, &mut batch);
+
+        Ok((immediate_id, scheduled_id))
+    }
+
+    /// Split transfer into any number of tranches, each sent now or locked until its time
+    /// Tranches are percentages of or amounts out of `total_amount` and must add up to it
+    /// The fee is charged once on `total_amount` and shared in proportion;
+    /// rounding dust goes to the last tranche
+    /// Returns the ID of the batch grouping the tranches
+    pub fn transfer_split_tranches(
+        env: Env,
+        from: Address,
+        to: Address,
+        token: Address,
+        total_amount: i128,
+        tranches: Vec<Tranche>,
+    ) -> Result<u64, SaveXError> {
+        require_not_paused(&env)?;
+
+        from.require_auth();
+
+        if total_amount <= 0 {
+            return Err(SaveXError::InvalidAmount);
+        }
+
+        if tranches.is_empty() {
+            return Err(SaveXError::EmptyBatch);
+        }
+
+        if tranches.len() > MAX_BATCH_SIZE {
+            return Err(SaveXError::BatchTooLarge);
+        }
+
+        // Amounts plus percentages of the total must cover exactly 100%
+        let current_time = env.ledger().timestamp();
+        let mut percent_sum: i128 = 0;
+        let mut amount_sum: i128 = 0;
+        for tranche in tranches.iter() {
+            if tranche.value <= 0 {
+                return Err(SaveXError::InvalidAmount);
+            }
+
+            if tranche.execute_after > 0 && tranche.execute_after <= current_time {
+                return Err(SaveXError::InvalidTimestamp);
+            }
+
+            match tranche.kind {
+                TrancheKind::Percentage => {
+                    if tranche.value > 100 {
+                        return Err(SaveXError::InvalidPercentage);
+                    }
+                    percent_sum += tranche.value;
+                }
+                TrancheKind::Amount => amount_sum += tranche.value,
+            }
+        }
+
+        if percent_sum > 100 || amount_sum * 100 + percent_sum * total_amount != total_amount * 100 {
+            return Err(SaveXError::InvalidAmount);
+        }
+
+        let token_info = require_supported_token(&env, &token)?;
+        let fee = collect_fee(&env, &from, &token_info, total_amount, false, 1)?;
+        record_package_usage(&env, &from, total_amount);
+
+        let token_client = token::Client::new(&env, &token);
+        let mut batch = new_batch(&env, &from);
+        let mut gross_left = total_amount;
+        let mut fee_left = fee;
+
+        for (i, tranche) in tranches.iter().enumerate() {
+            let is_last = i as u32 == tranches.len() - 1;
+
+            let gross = if is_last {
+                gross_left
+            } else {
+                match tranche.kind {
+                    TrancheKind::Percentage => total_amount * tranche.value / 100,
+                    TrancheKind::Amount => tranche.value,
+                }
+            };
+            let tranche_fee = if is_last { fee_left } else { fee * gross / total_amount };
+            gross_left -= gross;
+            fee_left -= tranche_fee;
+
+            let net_amount = gross - tranche_fee;
+            if net_amount <= 0 {
+                return Err(SaveXError::FeeExceedsAmount);
+            }
+
+            // Future tranches are escrowed until they are due
+            let receiver = if tranche.execute_after > 0 {
+                env.current_contract_address()
+            } else {
+                to.clone()
+            };
+            token_client.transfer(&from, &receiver, &net_amount);
+
+            let mut transfer = new_transfer(&env, &from, &to, &token, net_amount, TransferType::Split, tranche.execute_after);
+            save_batch_leg(&env, &mut batch, &mut transfer, gross);
+            record_fee_receipt(&env, transfer.id, &from, &token, gross, tranche_fee);
+        }
+
+        save_new_batch(&env, &mut batch);
+
+        Ok(batch.id)
+    }
+
+    // ========================================================================
+    // Batch Transfer
+    // ========================================================================
+
+    /// Batch multiple transfers to save on fees
+    /// Each line pays the batch-discounted fee for the batch size
+    /// The transfers are grouped in a batch record, see `Transfer::batch_id`
+    /// Returns vector of transfer IDs
+    pub fn transfer_batch(
+        env: Env,
+        from: Address,
+        recipients: soroban_sdk::Vec<Address>,
+        token: Address,
+        amounts: soroban_sdk::Vec<i128>,
+    ) -> Result<soroban_sdk::Vec<u64>, SaveXError> {
+        require_not_paused(&env)?;
+
+        from.require_auth();
+
+        if recipients.len() != amounts.len() {
+            return Err(SaveXError::LengthMismatch);
+        }
+
+        if recipients.is_empty() {
+            return Err(SaveXError::EmptyBatch);
+        }
+
+        if recipients.len() > MAX_BATCH_SIZE {
+            return Err(SaveXError::BatchTooLarge);
+        }
+
+        let token_info = require_supported_token(&env, &token)?;
+        let batch_size = recipients.len();
+        let mut transfer_ids = soroban_sdk::Vec::new(&env);
+        let mut batch = new_batch(&env, &from);
+
+        // Execute all transfers
+        for i in 0..recipients.len() {
+            let recipient = recipients.get(i).unwrap();
+            let amount = amounts.get(i).unwrap();
+
+            if amount <= 0 {
+                return Err(SaveXError::InvalidAmount);
+            }
+
+            let fee = collect_fee(&env, &from, &token_info, amount, true, batch_size)?;
+            let net_amount = amount - fee;
+            record_package_usage(&env, &from, amount);
+
+            // Execute transfer
+            token::Client::new(&env, &token).transfer(&from, &recipient, &net_amount);
+
+            // Store transfer record
+            let mut transfer = new_transfer(&env, &from, &recipient, &token, net_amount, TransferType::Batched, 0);
+            save_batch_leg(&env, &mut batch, &mut transfer, amount);
+            record_fee_receipt(&env, transfer.id, &from, &token, amount, fee);
+            transfer_ids.push_back(transfer.id);
+        }
+
+        save_new_batch(&env, &mut batch);
+
+        Ok(transfer_ids)
+    }
+
+    /// Create a batch of transfers across any registered tokens
+    /// Lines with an `execute_after` become locked transfers, the rest are sent now
+    /// Each line pays the batch-discounted fee for the batch size
+    /// Returns the batch ID
+    pub fn create_batch(env: Env, from: Address, entries: Vec<BatchEntry>) -> Result<u64, SaveXError> {
+        require_not_paused(&env)?;
+
+        from.require_auth();
+
+        if entries.is_empty() {
+            return Err(SaveXError::EmptyBatch);
+        }
+
+        if entries.len() > MAX_BATCH_SIZE {
+            return Err(SaveXError::BatchTooLarge);
+        }
+
+        let current_time = env.ledger().timestamp();
+        let batch_size = entries.len();
+        let mut batch = new_batch(&env, &from);
+
+        for entry in entries.iter() {
+            if entry.amount <= 0 {
+                return Err(SaveXError::InvalidAmount);
+            }
+
+            if entry.execute_after > 0 && entry.execute_after <= current_time {
+                return Err(SaveXError::InvalidTimestamp);
+            }
+
+            let token_info = require_supported_token(&env, &entry.token)?;
+            let fee = collect_fee(&env, &from, &token_info, entry.amount, true, batch_size)?;
+            let net_amount = entry.amount - fee;
+            record_package_usage(&env, &from, entry.amount);
+
+            // Scheduled lines are escrowed until they are due
+            let receiver = if entry.execute_after > 0 {
+                env.current_contract_address()
+            } else {
+                entry.recipient.clone()
+            };
+            token::Client::new(&env, &entry.token).transfer(&from, &receiver, &net_amount);
 
-        if admin != stored_admin {
-            panic!("Not authorized");
+            let mut transfer = new_transfer(
+                &env,
+                &from,
+                &entry.recipient,
+                &entry.token,
+                net_amount,
+                TransferType::Batched,
+                entry.execute_after,
+            );
+            save_batch_leg(&env, &mut batch, &mut transfer, entry.amount);
+            record_fee_receipt(&env, transfer.id, &from, &entry.token, entry.amount, fee);
         }
 
-        env.storage().instance().set(&DataKey::IsPaused, &true);
+        save_new_batch(&env, &mut batch);
+
+        Ok(batch.id)
     }
 
-    /// Unpause the contract (admin only)
-    pub fn unpause(env: Env, admin: Address) {
-        admin.require_auth();
+    /// Execute every scheduled leg of a batch that is due (anyone can call)
+    /// Legs that are settled or no longer in storage are skipped
+    /// Returns the number of legs executed
+    pub fn execute_batch(env: Env, batch_id: u64) -> Result<u32, SaveXError> {
+        require_not_paused(&env)?;
 
-        let stored_admin: Address = env.storage()
-            .instance()
-            .get(&DataKey::Admin)
-            .expect("Not initialized");
+        let batch = load_batch(&env, batch_id)?;
 
-        if admin != stored_admin {
-            panic!("Not authorized");
+        if batch.status != BatchStatus::Pending {
+            return Err(SaveXError::TransferNotPending);
         }
 
-        env.storage().instance().set(&DataKey::IsPaused, &false);
+        let mut executed = 0u32;
+        for transfer_id in batch.transfer_ids.iter() {
+            // One missing leg must not hold up the others
+            let Ok(mut transfer) = load_transfer(&env, transfer_id) else {
+                continue;
+            };
+            if is_due_scheduled(&env, &transfer) {
+                settle_locked_transfer(&env, &mut transfer, true);
+                executed += 1;
+            }
+        }
+
+        events::BatchExecuted {
+            id: batch_id,
+            executed,
+            pending_legs: batch.pending_legs - executed,
+        }
+        .publish(&env);
+
+        Ok(executed)
     }
 
-    /// Set Soroswap Router address for swap functionality (admin only)
-    pub fn set_router_address(env: Env, admin: Address, router: Address) {
-        admin.require_auth();
+    /// Cancel a batch, refunding every leg that is still locked (creator only)
+    /// Legs that are settled or no longer in storage are skipped
+    /// Returns the number of legs refunded
+    pub fn cancel_batch(env: Env, creator: Address, batch_id: u64) -> Result<u32, SaveXError> {
+        creator.require_auth();
 
-        let stored_admin: Address = env.storage()
-            .instance()
-            .get(&DataKey::Admin)
-            .expect("Not initialized");
+        let batch = load_batch(&env, batch_id)?;
 
-        if admin != stored_admin {
-            panic!("Not authorized");
+        if batch.creator != creator {
+            return Err(SaveXError::NotAuthorized);
         }
 
-        env.storage().instance().set(&DataKey::RouterAddress, &router);
-    }
+        if batch.status != BatchStatus::Pending {
+            return Err(SaveXError::TransferNotPending);
+        }
 
-    /// Get configured Soroswap Router address
-    pub fn get_router_address(env: Env) -> Address {
-        env.storage()
-            .instance()
-            .get(&DataKey::RouterAddress)
-            .expect("Router address not configured")
-    }
+        let mut cancelled = 0u32;
+        for transfer_id in batch.transfer_ids.iter() {
+            let Ok(mut transfer) = load_transfer(&env, transfer_id) else {
+                continue;
+            };
+            if transfer.status == TransferStatus::Locked {
+                settle_locked_transfer(&env, &mut transfer, false);
+                cancelled += 1;
+            }
+        }
 
-    /// Set Soroswap Factory address for pool queries (admin only)
-    pub fn set_factory_address(env: Env, admin: Address, factory: Address) {
-        admin.require_auth();
+        // Reload, settling the legs updated the pending count
+        let mut batch = load_batch(&env, batch_id)?;
+        batch.status = BatchStatus::Cancelled;
+        store_batch(&env, &batch);
 
-        let stored_admin: Address = env.storage()
-            .instance()
-            .get(&DataKey::Admin)
-            .expect("Not initialized");
+        events::BatchCancelled { id: batch_id, creator, cancelled }.publish(&env);
 
-        if admin != stored_admin {
-            panic!("Not authorized");
-        }
+        Ok(cancelled)
+    }
 
-        env.storage().instance().set(&DataKey::FactoryAddress, &factory);
+    /// Get batch details
+    pub fn get_batch(env: Env, batch_id: u64) -> Result<Batch, SaveXError> {
+        load_batch(&env, batch_id)
     }
 
-    /// Get configured Soroswap Factory address
-    pub fn get_factory_address(env: Env) -> Address {
-        env.storage()
-            .instance()
-            .get(&DataKey::FactoryAddress)
-            .expect("Factory address not configured")
+    /// Get the transfer records of a batch
+    pub fn get_batch_transfers(env: Env, batch_id: u64) -> Result<Vec<Transfer>, SaveXError> {
+        let batch = load_batch(&env, batch_id)?;
+        Ok(load_transfers(&env, batch.transfer_ids))
     }
 
     // ========================================================================
-    // Rate Locking Module
+    // Conditional Escrow
     // ========================================================================
 
-    /// Lock an exchange rate for future use
-    /// Rate should be provided with 7 decimal precision (e.g., 1.5 = 15000000)
-    pub fn lock_rate(
+    /// Create an escrow transfer released by the arbiter, or by `threshold` of the approvers
+    /// Pass no approvers and a threshold of 0 for arbiter-only release
+    /// The sender can take the funds back once `refund_after` has passed
+    pub fn transfer_escrow(
         env: Env,
-        owner: Address,
-        from_token: Address,
-        to_token: Address,
-        locked_rate: i128,
+        from: Address,
+        to: Address,
+        token: Address,
         amount: i128,
-        duration_seconds: u64,
-    ) -> u64 {
-        if is_paused(&env) {
-            panic!("Contract is paused");
+        release: EscrowRelease,
+        refund_after: u64,
+    ) -> Result<u64, SaveXError> {
+        require_not_paused(&env)?;
+
+        from.require_auth();
+
+        if refund_after <= env.ledger().timestamp() {
+            return Err(SaveXError::InvalidTimestamp);
         }
 
-        owner.require_auth();
+        let approvers = &release.approvers;
+        if approvers.len() > MAX_APPROVERS
+            || release.threshold > approvers.len()
+            || (release.threshold == 0) != approvers.is_empty()
+        {
+            return Err(SaveXError::InvalidApprovers);
+        }
 
-        if amount <= 0 {
-            panic!("Invalid amount");
+        for (i, approver) in approvers.iter().enumerate() {
+            if approvers.first_index_of(&approver) != Some(i as u32) {
+                return Err(SaveXError::InvalidApprovers);
+            }
         }
 
-        if locked_rate <= 0 {
-            panic!("Invalid rate");
+        let (net_amount, fee) = escrow_transfer_amount(&env, &from, &token, amount)?;
+
+        let mut transfer = new_transfer(&env, &from, &to, &token, net_amount, TransferType::Escrow, 0);
+        transfer.has_time_bound = true;
+        transfer.time_bound_kind = TimeBoundKind::Before;
+        transfer.time_bound_timestamp = refund_after;
+        transfer.status = TransferStatus::Locked;
+        save_new_transfer(&env, &transfer);
+        record_fee_receipt(&env, transfer.id, &from, &token, amount, fee);
+
+        store_escrow_terms(&env, &EscrowTerms {
+            transfer_id: transfer.id,
+            release: release.clone(),
+            approvals: Vec::new(&env),
+        });
+
+        events::EscrowCreated {
+            id: transfer.id,
+            release,
+            refund_after,
         }
+        .publish(&env);
 
-        // Maximum lock duration: 24 hours
-        if duration_seconds > 86400 {
-            panic!("Duration too long (max 24h)");
+        Ok(transfer.id)
+    }
+
+    /// Approve the release of an escrow before its deadline
+    /// The arbiter releases at once; approvers release when the threshold is met
+    /// Returns true once the funds have been released
+    pub fn approve_escrow(env: Env, approver: Address, transfer_id: u64) -> Result<bool, SaveXError> {
+        require_not_paused(&env)?;
+
+        approver.require_auth();
+
+        let mut transfer = load_transfer(&env, transfer_id)?;
+        let mut terms = load_escrow_terms(&env, transfer_id)?;
+
+        if transfer.status != TransferStatus::Locked {
+            return Err(SaveXError::TransferNotPending);
         }
 
-        let current_time = env.ledger().timestamp();
-        let expiry = current_time + duration_seconds;
-        let id = next_rate_lock_id(&env);
+        let deadline = TimeBound {
+            kind: transfer.time_bound_kind.clone(),
+            timestamp: transfer.time_bound_timestamp,
+        };
 
-        let rate_lock = RateLock {
-            id,
-            owner: owner.clone(),
-            from_token,
-            to_token,
-            locked_rate,
-            amount,
-            expiry,
-            is_active: true,
-            created_at: current_time,
+        if !check_time_bound(&env, &deadline) {
+            return Err(SaveXError::TimeBoundNotMet);
+        }
+
+        let release = if approver == terms.release.arbiter {
+            true
+        } else if terms.release.approvers.contains(&approver) {
+            if !terms.approvals.contains(&approver) {
+                terms.approvals.push_back(approver.clone());
+                store_escrow_terms(&env, &terms);
+            }
+            terms.approvals.len() >= terms.release.threshold
+        } else {
+            return Err(SaveXError::NotAuthorized);
         };
 
-        env.storage().persistent().set(&PersistentDataKey::RateLock(id), &rate_lock);
-        env.storage().persistent().extend_ttl(&PersistentDataKey::RateLock(id), INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+        events::EscrowApproved {
+            id: transfer_id,
+            approver,
+            approvals: terms.approvals.len(),
+        }
+        .publish(&env);
 
-        id
-    }
+        if release {
+            settle_locked_transfer(&env, &mut transfer, true);
+        }
 
-    /// Get rate lock details
-    pub fn get_rate_lock(env: Env, lock_id: u64) -> RateLock {
-        env.storage()
-            .persistent()
-            .get(&PersistentDataKey::RateLock(lock_id))
-            .expect("Rate lock not found")
+        Ok(release)
     }
 
-    /// Cancel rate lock (owner only)
-    pub fn cancel_rate_lock(env: Env, owner: Address, lock_id: u64) {
-        owner.require_auth();
+    /// Freeze an escrow until the arbiter resolves it (sender or recipient only)
+    /// Disputes must be raised before the refund deadline
+    pub fn dispute_escrow(env: Env, caller: Address, transfer_id: u64) -> Result<(), SaveXError> {
+        caller.require_auth();
 
-        let mut rate_lock: RateLock = env.storage()
-            .persistent()
-            .get(&PersistentDataKey::RateLock(lock_id))
-            .expect("Rate lock not found");
+        let mut transfer = load_transfer(&env, transfer_id)?;
+        load_escrow_terms(&env, transfer_id)?;
 
-        if rate_lock.owner != owner {
-            panic!("Not authorized");
+        if caller != transfer.from && caller != transfer.to {
+            return Err(SaveXError::NotAuthorized);
         }
 
-        if !rate_lock.is_active {
-            panic!("Rate lock not active");
+        if transfer.status != TransferStatus::Locked {
+            return Err(SaveXError::TransferNotPending);
         }
 
-        rate_lock.is_active = false;
-        env.storage().persistent().set(&PersistentDataKey::RateLock(lock_id), &rate_lock);
-        env.storage().persistent().extend_ttl(&PersistentDataKey::RateLock(lock_id), INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
-    }
+        let deadline = TimeBound {
+            kind: transfer.time_bound_kind.clone(),
+            timestamp: transfer.time_bound_timestamp,
+        };
 
-    // ========================================================================
-    // Transfer Module
-    // ========================================================================
+        if !check_time_bound(&env, &deadline) {
+            return Err(SaveXError::TimeBoundNotMet);
+        }
 
-    /// Create an immediate transfer
-    pub fn transfer_immediate(
+        transfer.status = TransferStatus::Disputed;
+        store_transfer(&env, &transfer);
+
+        events::EscrowDisputed { id: transfer_id, raised_by: caller }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Settle a disputed escrow, paying the recipient or refunding the sender (arbiter only)
+    /// Disputes can be resolved after the refund deadline
+    pub fn resolve_dispute(
         env: Env,
-        from: Address,
-        to: Address,
-        token: Address,
-        amount: i128,
-    ) -> u64 {
-        if is_paused(&env) {
-            panic!("Contract is paused");
-        }
+        arbiter: Address,
+        transfer_id: u64,
+        release: bool,
+    ) -> Result<(), SaveXError> {
+        arbiter.require_auth();
 
-        from.require_auth();
+        let mut transfer = load_transfer(&env, transfer_id)?;
+        let terms = load_escrow_terms(&env, transfer_id)?;
 
-        if amount <= 0 {
-            panic!("Invalid amount");
+        if arbiter != terms.release.arbiter {
+            return Err(SaveXError::NotAuthorized);
         }
 
-        let transfer_id = next_transfer_id(&env);
-        let current_time = env.ledger().timestamp();
+        if transfer.status != TransferStatus::Disputed {
+            return Err(SaveXError::TransferNotPending);
+        }
 
-        // Execute transfer immediately
-        token::Client::new(&env, &token).transfer(&from, &to, &amount);
+        settle_locked_transfer(&env, &mut transfer, release);
 
-        // Store transfer record
-        let transfer = Transfer {
-            id: transfer_id,
-            from: from.clone(),
-            to: to.clone(),
-            token: token.clone(),
-            amount,
-            transfer_type: TransferType::Immediate,
-            has_time_bound: false,
-            time_bound_kind: TimeBoundKind::After,
-            time_bound_timestamp: 0,
-            status: TransferStatus::Completed,
-            created_at: current_time,
-            has_rate_lock: false,
-            rate_lock_id: 0,
+        events::EscrowResolved { id: transfer_id, arbiter, released: release }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Refund an unreleased escrow to its sender after the deadline
+    pub fn refund_escrow(env: Env, owner: Address, transfer_id: u64) -> Result<(), SaveXError> {
+        owner.require_auth();
+
+        let mut transfer = load_transfer(&env, transfer_id)?;
+        load_escrow_terms(&env, transfer_id)?;
+
+        if transfer.from != owner {
+            return Err(SaveXError::NotAuthorized);
+        }
+
+        if transfer.status != TransferStatus::Locked {
+            return Err(SaveXError::TransferNotPending);
+        }
+
+        let deadline = TimeBound {
+            kind: transfer.time_bound_kind.clone(),
+            timestamp: transfer.time_bound_timestamp,
         };
 
-        env.storage().persistent().set(&PersistentDataKey::Transfer(transfer_id), &transfer);
-        env.storage().persistent().extend_ttl(&PersistentDataKey::Transfer(transfer_id), INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+        if check_time_bound(&env, &deadline) {
+            return Err(SaveXError::TimeBoundNotMet);
+        }
+
+        settle_locked_transfer(&env, &mut transfer, false);
 
-        transfer_id
+        Ok(())
     }
 
-    /// Create a scheduled (time-locked) transfer
-    pub fn transfer_scheduled(
+    /// Get the release conditions and approvals of an escrow
+    pub fn get_escrow_terms(env: Env, transfer_id: u64) -> Result<EscrowTerms, SaveXError> {
+        load_escrow_terms(&env, transfer_id)
+    }
+
+    // ========================================================================
+    // Streaming Payments
+    // ========================================================================
+
+    /// Stream `amount` to `to`, vesting linearly from `start` to `end`
+    /// The fee is charged up front; only the net amount is escrowed and streamed
+    pub fn transfer_stream(
         env: Env,
         from: Address,
         to: Address,
         token: Address,
         amount: i128,
-        execute_after: u64,
-    ) -> u64 {
-        if is_paused(&env) {
-            panic!("Contract is paused");
-        }
+        start: u64,
+        end: u64,
+    ) -> Result<u64, SaveXError> {
+        require_not_paused(&env)?;
 
         from.require_auth();
 
-        if amount <= 0 {
-            panic!("Invalid amount");
+        if start < env.ledger().timestamp() {
+            return Err(SaveXError::InvalidTimestamp);
         }
 
-        let current_time = env.ledger().timestamp();
-
-        if execute_after <= current_time {
-            panic!("Invalid timestamp (must be in future)");
+        if end <= start {
+            return Err(SaveXError::InvalidSchedule);
         }
 
-        let transfer_id = next_transfer_id(&env);
+        let (net_amount, fee) = escrow_transfer_amount(&env, &from, &token, amount)?;
 
-        // Lock tokens in contract
-        token::Client::new(&env, &token).transfer(
-            &from,
-            &env.current_contract_address(),
-            &amount
-        );
+        let mut transfer = new_transfer(&env, &from, &to, &token, net_amount, TransferType::Stream, start);
+        transfer.has_time_bound = true;
+        transfer.status = TransferStatus::Locked;
+        save_new_transfer(&env, &transfer);
+        record_fee_receipt(&env, transfer.id, &from, &token, amount, fee);
 
-        // Store transfer record
-        let transfer = Transfer {
-            id: transfer_id,
-            from: from.clone(),
-            to: to.clone(),
-            token: token.clone(),
-            amount,
-            transfer_type: TransferType::Scheduled,
-            has_time_bound: true,
-            time_bound_kind: TimeBoundKind::After,
-            time_bound_timestamp: execute_after,
-            status: TransferStatus::Locked,
-            created_at: current_time,
-            has_rate_lock: false,
-            rate_lock_id: 0,
-        };
+        store_stream(&env, &StreamSchedule {
+            transfer_id: transfer.id,
+            start,
+            end,
+            withdrawn: 0,
+        });
 
-        env.storage().persistent().set(&PersistentDataKey::Transfer(transfer_id), &transfer);
-        env.storage().persistent().extend_ttl(&PersistentDataKey::Transfer(transfer_id), INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+        events::StreamCreated { id: transfer.id, start, end }.publish(&env);
 
-        transfer_id
+        Ok(transfer.id)
     }
 
-    /// Execute a scheduled transfer (anyone can call, but only after time)
-    pub fn execute_scheduled_transfer(env: Env, transfer_id: u64) {
-        if is_paused(&env) {
-            panic!("Contract is paused");
-        }
+    /// Withdraw everything vested so far (recipient only)
+    /// Returns the amount withdrawn
+    pub fn withdraw_stream(env: Env, recipient: Address, transfer_id: u64) -> Result<i128, SaveXError> {
+        require_not_paused(&env)?;
 
-        let mut transfer: Transfer = env.storage()
-            .persistent()
-            .get(&PersistentDataKey::Transfer(transfer_id))
-            .expect("Transfer not found");
+        recipient.require_auth();
 
-        if transfer.status != TransferStatus::Locked {
-            panic!("Transfer not in locked state");
-        }
+        let mut transfer = load_transfer(&env, transfer_id)?;
+        let mut stream = load_stream(&env, transfer_id)?;
 
-        if !transfer.has_time_bound {
-            panic!("No time bound set");
+        if transfer.to != recipient {
+            return Err(SaveXError::NotAuthorized);
         }
 
-        let time_bound = TimeBound {
-            kind: transfer.time_bound_kind.clone(),
-            timestamp: transfer.time_bound_timestamp,
-        };
+        if transfer.status != TransferStatus::Locked {
+            return Err(SaveXError::TransferNotPending);
+        }
 
-        if !check_time_bound(&env, &time_bound) {
-            panic!("Time condition not met");
+        let available = vested_amount(&env, &transfer, &stream) - stream.withdrawn;
+        if available <= 0 {
+            return Err(SaveXError::InsufficientBalance);
         }
 
-        // Execute the transfer
         token::Client::new(&env, &transfer.token).transfer(
             &env.current_contract_address(),
-            &transfer.to,
-            &transfer.amount
+            &recipient,
+            &available
         );
 
-        // Update status
-        transfer.status = TransferStatus::Completed;
-        env.storage().persistent().set(&PersistentDataKey::Transfer(transfer_id), &transfer);
-        env.storage().persistent().extend_ttl(&PersistentDataKey::Transfer(transfer_id), INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+        stream.withdrawn += available;
+        store_stream(&env, &stream);
+
+        events::StreamWithdrawn {
+            id: transfer_id,
+            to: recipient,
+            amount: available,
+            withdrawn: stream.withdrawn,
+        }
+        .publish(&env);
+
+        // Fully withdrawn streams are complete
+        if stream.withdrawn == transfer.amount {
+            transfer.status = TransferStatus::Completed;
+            store_transfer(&env, &transfer);
+            events::transfer_executed(&env, &transfer);
+        }
+
+        Ok(available)
     }
 
-    /// Cancel a scheduled transfer (owner only, before execution)
-    pub fn cancel_scheduled_transfer(env: Env, owner: Address, transfer_id: u64) {
+    /// Cancel a stream (sender only)
+    /// Vested tokens not yet withdrawn go to the recipient, the unvested rest back to the sender
+    pub fn cancel_stream(env: Env, owner: Address, transfer_id: u64) -> Result<(), SaveXError> {
         owner.require_auth();
 
-        let mut transfer: Transfer = env.storage()
-            .persistent()
-            .get(&PersistentDataKey::Transfer(transfer_id))
-            .expect("Transfer not found");
+        let mut transfer = load_transfer(&env, transfer_id)?;
+        let mut stream = load_stream(&env, transfer_id)?;
 
         if transfer.from != owner {
-            panic!("Not authorized");
+            return Err(SaveXError::NotAuthorized);
         }
 
         if transfer.status != TransferStatus::Locked {
-            panic!("Transfer not in locked state");
+            return Err(SaveXError::TransferNotPending);
         }
 
-        // Refund tokens
-        token::Client::new(&env, &transfer.token).transfer(
-            &env.current_contract_address(),
-            &transfer.from,
-            &transfer.amount
-        );
+        let vested = vested_amount(&env, &transfer, &stream);
+        let paid_out = vested - stream.withdrawn;
+        let refund = transfer.amount - vested;
+
+        let token_client = token::Client::new(&env, &transfer.token);
+        let contract = env.current_contract_address();
+        if paid_out > 0 {
+            token_client.transfer(&contract, &transfer.to, &paid_out);
+        }
+        if refund > 0 {
+            token_client.transfer(&contract, &transfer.from, &refund);
+        }
+
+        stream.withdrawn = vested;
+        store_stream(&env, &stream);
 
-        // Update status
         transfer.status = TransferStatus::Cancelled;
-        env.storage().persistent().set(&PersistentDataKey::Transfer(transfer_id), &transfer);
-        env.storage().persistent().extend_ttl(&PersistentDataKey::Transfer(transfer_id), INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+        store_transfer(&env, &transfer);
+        events::transfer_cancelled(&env, &transfer);
+
+        events::StreamCancelled { id: transfer_id, paid_out, refund }.publish(&env);
+
+        Ok(())
     }
 
-    /// Get transfer details
-    pub fn get_transfer(env: Env, transfer_id: u64) -> Transfer {
-        env.storage()
-            .persistent()
-            .get(&PersistentDataKey::Transfer(transfer_id))
-            .expect("Transfer not found")
+    /// Get the vesting schedule of a stream
+    pub fn get_stream(env: Env, transfer_id: u64) -> Result<StreamSchedule, SaveXError> {
+        load_stream(&env, transfer_id)
+    }
+
+    /// Get the amount the recipient of a stream can withdraw now
+    pub fn get_stream_withdrawable(env: Env, transfer_id: u64) -> Result<i128, SaveXError> {
+        let transfer = load_transfer(&env, transfer_id)?;
+        let stream = load_stream(&env, transfer_id)?;
+
+        if transfer.status != TransferStatus::Locked {
+            return Ok(0);
+        }
+
+        Ok(vested_amount(&env, &transfer, &stream) - stream.withdrawn)
     }
 
     // ========================================================================
-    // Transfer with Rate Lock
+    // Recurring Transfers
     // ========================================================================
 
-    /// Create a transfer using a locked rate
-    pub fn transfer_with_rate_lock(
+    /// Create a standing order paying `amount` to `to` every `interval_seconds`
+    /// All installments are escrowed up front; the fee is charged per installment
+    /// The first installment is due one interval from now
+    pub fn create_recurring_transfer(
         env: Env,
         from: Address,
         to: Address,
         token: Address,
         amount: i128,
-        rate_lock_id: u64,
-    ) -> u64 {
-        if is_paused(&env) {
-            panic!("Contract is paused");
-        }
+        interval_seconds: u64,
+        occurrences: u32,
+    ) -> Result<u64, SaveXError> {
+        require_not_paused(&env)?;
 
         from.require_auth();
 
         if amount <= 0 {
-            panic!("Invalid amount");
-        }
-
-        // Validate rate lock
-        let rate_lock: RateLock = env.storage()
-            .persistent()
-            .get(&PersistentDataKey::RateLock(rate_lock_id))
-            .expect("Rate lock not found");
-
-        if rate_lock.owner != from {
-            panic!("Not rate lock owner");
-        }
-
-        if !rate_lock.is_active {
-            panic!("Rate lock not active");
-        }
-
-        let current_time = env.ledger().timestamp();
-        if current_time > rate_lock.expiry {
-            panic!("Rate lock expired");
+            return Err(SaveXError::InvalidAmount);
         }
 
-        if amount > rate_lock.amount {
-            panic!("Amount exceeds locked amount");
+        if interval_seconds == 0 || occurrences == 0 || occurrences > MAX_OCCURRENCES {
+            return Err(SaveXError::InvalidSchedule);
         }
 
-        let transfer_id = next_transfer_id(&env);
+        require_supported_token(&env, &token)?;
 
-        // Execute transfer immediately with locked rate
-        // Note: Rate lock only locks the rate, actual swap still uses current pool state
-        // This is a design limitation - true rate locking requires hedging mechanisms
-        token::Client::new(&env, &token).transfer(&from, &to, &amount);
+        token::Client::new(&env, &token).transfer(
+            &from,
+            env.current_contract_address(),
+            &(amount * occurrences as i128)
+        );
 
-        // Store transfer record
-        let transfer = Transfer {
-            id: transfer_id,
+        let current_time = env.ledger().timestamp();
+        let recurring = RecurringTransfer {
+            id: next_recurring_id(&env),
             from: from.clone(),
             to: to.clone(),
             token: token.clone(),
             amount,
-            transfer_type: TransferType::Immediate,
-            has_time_bound: false,
-            time_bound_kind: TimeBoundKind::After,
-            time_bound_timestamp: 0,
-            status: TransferStatus::Completed,
-            created_at: current_time,
-            has_rate_lock: true,
-            rate_lock_id,
-        };
-
-        env.storage().persistent().set(&PersistentDataKey::Transfer(transfer_id), &transfer);
-        env.storage().persistent().extend_ttl(&PersistentDataKey::Transfer(transfer_id), INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
-
-        transfer_id
-    }
-
-    // ========================================================================
-    // Split Transfer
-    // ========================================================================
-
-    /// Split transfer: Send part now, part later
-    /// Returns tuple of (immediate_transfer_id, scheduled_transfer_id)
-    pub fn transfer_split(
-        env: Env,
-        from: Address,
-        to: Address,
-        token: Address,
-        total_amount: i128,
-        immediate_percentage: u32,  // 0-100, e.g., 50 = 50%
-        schedule_after: u64,
-    ) -> (u64, u64) {
-        if is_paused(&env) {
-            panic!("Contract is paused");
+            interval: interval_seconds,
+            occurrences,
+            executed_count: 0,
+            next_execution: current_time + interval_seconds,
+            status: RecurringStatus::Active,
+            paused_at: 0,
+            created_at: current_time,
+            transfers: Vec::new(&env),
+        };
+        store_recurring(&env, &recurring);
+
+        events::RecurringCreated {
+            id: recurring.id,
+            from,
+            to,
+            token,
+            amount,
+            interval: interval_seconds,
+            occurrences,
+            first_execution: recurring.next_execution,
         }
+        .publish(&env);
 
-        from.require_auth();
+        Ok(recurring.id)
+    }
 
-        if total_amount <= 0 {
-            panic!("Invalid amount");
+    /// Pay out the next installment of a recurring transfer once it is due
+    /// Anyone can call this; missed installments can be caught up one call at a time
+    /// Returns the ID of the installment's transfer record
+    pub fn execute_due_installment(env: Env, recurring_id: u64) -> Result<u64, SaveXError> {
+        require_not_paused(&env)?;
+
+        let mut recurring = load_recurring(&env, recurring_id)?;
+
+        if recurring.status != RecurringStatus::Active {
+            return Err(SaveXError::TransferNotPending);
         }
 
-        if immediate_percentage > 100 {
-            panic!("Invalid percentage (max 100)");
+        if env.ledger().timestamp() < recurring.next_execution {
+            return Err(SaveXError::TimeBoundNotMet);
         }
 
-        let current_time = env.ledger().timestamp();
-        if schedule_after <= current_time {
-            panic!("Invalid timestamp (must be in future)");
+        // The fee is owed by the sender but paid from the escrow
+        let contract = env.current_contract_address();
+        let token_info = require_supported_token(&env, &recurring.token)?;
+        let fee = collect_fee_from(&env, &recurring.from, &contract, &token_info, recurring.amount, false, 1)?;
+        let net_amount = recurring.amount - fee;
+        record_package_usage(&env, &recurring.from, recurring.amount);
+
+        token::Client::new(&env, &recurring.token).transfer(&contract, &recurring.to, &net_amount);
+
+        let transfer = new_transfer(
+            &env,
+            &recurring.from,
+            &recurring.to,
+            &recurring.token,
+            net_amount,
+            TransferType::Recurring,
+            0,
+        );
+        save_new_transfer(&env, &transfer);
+        record_fee_receipt(&env, transfer.id, &recurring.from, &recurring.token, recurring.amount, fee);
+
+        recurring.executed_count += 1;
+        recurring.transfers.push_back(transfer.id);
+        if recurring.executed_count == recurring.occurrences {
+            recurring.status = RecurringStatus::Completed;
+            recurring.next_execution = 0;
+        } else {
+            recurring.next_execution += recurring.interval;
         }
+        store_recurring(&env, &recurring);
 
-        // Calculate split amounts
-        let immediate_amount = (total_amount * immediate_percentage as i128) / 100;
-        let scheduled_amount = total_amount - immediate_amount;
+        events::RecurringInstallmentExecuted {
+            id: recurring_id,
+            transfer_id: transfer.id,
+            installment: recurring.executed_count,
+            next_execution: recurring.next_execution,
+        }
+        .publish(&env);
 
-        // Execute immediate transfer
-        let immediate_id = if immediate_amount > 0 {
-            let id = next_transfer_id(&env);
-            token::Client::new(&env, &token).transfer(&from, &to, &immediate_amount);
+        Ok(transfer.id)
+    }
 
-            let transfer = Transfer {
-                id,
-                from: from.clone(),
-                to: to.clone(),
-                token: token.clone(),
-                amount: immediate_amount,
-                transfer_type: TransferType::Split,
-                has_time_bound: false,
-                time_bound_kind: TimeBoundKind::After,
-                time_bound_timestamp: 0,
-                status: TransferStatus::Completed,
-                created_at: current_time,
-                has_rate_lock: false,
-                rate_lock_id: 0,
-            };
+    /// Pause a recurring transfer (sender only)
+    pub fn pause_recurring_transfer(env: Env, from: Address, recurring_id: u64) -> Result<(), SaveXError> {
+        from.require_auth();
 
-            env.storage().persistent().set(&PersistentDataKey::Transfer(id), &transfer);
-            env.storage().persistent().extend_ttl(&PersistentDataKey::Transfer(id), INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
-            id
-        } else {
-            0
-        };
+        let mut recurring = load_recurring(&env, recurring_id)?;
 
-        // Create scheduled transfer
-        let scheduled_id = if scheduled_amount > 0 {
-            let id = next_transfer_id(&env);
+        if recurring.from != from {
+            return Err(SaveXError::NotAuthorized);
+        }
 
-            // Lock tokens in contract
-            token::Client::new(&env, &token).transfer(
-                &from,
-                &env.current_contract_address(),
-                &scheduled_amount
-            );
+        if recurring.status != RecurringStatus::Active {
+            return Err(SaveXError::TransferNotPending);
+        }
 
-            let transfer = Transfer {
-                id,
-                from: from.clone(),
-                to: to.clone(),
-                token: token.clone(),
-                amount: scheduled_amount,
-                transfer_type: TransferType::Split,
-                has_time_bound: true,
-                time_bound_kind: TimeBoundKind::After,
-                time_bound_timestamp: schedule_after,
-                status: TransferStatus::Locked,
-                created_at: current_time,
-                has_rate_lock: false,
-                rate_lock_id: 0,
-            };
+        recurring.status = RecurringStatus::Paused;
+        recurring.paused_at = env.ledger().timestamp();
+        store_recurring(&env, &recurring);
 
-            env.storage().persistent().set(&PersistentDataKey::Transfer(id), &transfer);
-            env.storage().persistent().extend_ttl(&PersistentDataKey::Transfer(id), INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
-            id
-        } else {
-            0
-        };
+        events::RecurringPaused { id: recurring_id, from }.publish(&env);
 
-        (immediate_id, scheduled_id)
+        Ok(())
     }
 
-    // ========================================================================
-    // Batch Transfer
-    // ========================================================================
-
-    /// Batch multiple transfers to save on fees
-    /// Returns vector of transfer IDs
-    pub fn transfer_batch(
-        env: Env,
-        from: Address,
-        recipients: soroban_sdk::Vec<Address>,
-        token: Address,
-        amounts: soroban_sdk::Vec<i128>,
-    ) -> soroban_sdk::Vec<u64> {
-        if is_paused(&env) {
-            panic!("Contract is paused");
-        }
-
+    /// Resume a paused recurring transfer (sender only)
+    /// The schedule is shifted by the time spent paused, so no installments pile up
+    pub fn resume_recurring_transfer(env: Env, from: Address, recurring_id: u64) -> Result<(), SaveXError> {
         from.require_auth();
 
-        if recipients.len() != amounts.len() {
-            panic!("Recipients and amounts length mismatch");
-        }
+        let mut recurring = load_recurring(&env, recurring_id)?;
 
-        if recipients.len() == 0 {
-            panic!("Empty batch");
+        if recurring.from != from {
+            return Err(SaveXError::NotAuthorized);
         }
 
-        if recipients.len() > 50 {
-            panic!("Batch too large (max 50)");
+        if recurring.status != RecurringStatus::Paused {
+            return Err(SaveXError::TransferNotPending);
         }
 
-        let current_time = env.ledger().timestamp();
-        let mut transfer_ids = soroban_sdk::Vec::new(&env);
+        recurring.next_execution += env.ledger().timestamp() - recurring.paused_at;
+        recurring.status = RecurringStatus::Active;
+        recurring.paused_at = 0;
+        store_recurring(&env, &recurring);
 
-        // Execute all transfers
-        for i in 0..recipients.len() {
-            let recipient = recipients.get(i).unwrap();
-            let amount = amounts.get(i).unwrap();
+        events::RecurringResumed {
+            id: recurring_id,
+            from,
+            next_execution: recurring.next_execution,
+        }
+        .publish(&env);
 
-            if amount <= 0 {
-                panic!("Invalid amount in batch");
-            }
+        Ok(())
+    }
 
-            // Execute transfer
-            token::Client::new(&env, &token).transfer(&from, &recipient, &amount);
+    /// Cancel a recurring transfer and refund the unpaid installments (sender only)
+    pub fn cancel_recurring_transfer(env: Env, from: Address, recurring_id: u64) -> Result<(), SaveXError> {
+        from.require_auth();
 
-            // Store transfer record
-            let transfer_id = next_transfer_id(&env);
-            let transfer = Transfer {
-                id: transfer_id,
-                from: from.clone(),
-                to: recipient,
-                token: token.clone(),
-                amount,
-                transfer_type: TransferType::Batched,
-                has_time_bound: false,
-                time_bound_kind: TimeBoundKind::After,
-                time_bound_timestamp: 0,
-                status: TransferStatus::Completed,
-                created_at: current_time,
-                has_rate_lock: false,
-                rate_lock_id: 0,
-            };
+        let mut recurring = load_recurring(&env, recurring_id)?;
 
-            env.storage().persistent().set(&PersistentDataKey::Transfer(transfer_id), &transfer);
-            env.storage().persistent().extend_ttl(&PersistentDataKey::Transfer(transfer_id), INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
-            transfer_ids.push_back(transfer_id);
+        if recurring.from != from {
+            return Err(SaveXError::NotAuthorized);
         }
 
-        transfer_ids
+        if recurring.status != RecurringStatus::Active && recurring.status != RecurringStatus::Paused {
+            return Err(SaveXError::TransferNotPending);
+        }
+
+        let refund = recurring.amount * (recurring.occurrences - recurring.executed_count) as i128;
+        token::Client::new(&env, &recurring.token).transfer(
+            &env.current_contract_address(),
+            &from,
+            &refund
+        );
+
+        recurring.status = RecurringStatus::Cancelled;
+        recurring.next_execution = 0;
+        store_recurring(&env, &recurring);
+
+        events::RecurringCancelled { id: recurring_id, from, refund }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Get recurring transfer details
+    pub fn get_recurring_transfer(env: Env, recurring_id: u64) -> Result<RecurringTransfer, SaveXError> {
+        load_recurring(&env, recurring_id)
     }
 
     // ========================================================================
     // Package System
     // ========================================================================
 
-    /// Subscribe to a package plan
+    /// Subscribe to a package at the admin-set daily price
+    /// The full `duration_days` is paid up front and held by the contract
     pub fn subscribe_package(
         env: Env,
         owner: Address,
         package_type: PackageType,
         duration_days: u32,
-    ) {
-        if is_paused(&env) {
-            panic!("Contract is paused");
-        }
+    ) -> Result<(), SaveXError> {
+        require_not_paused(&env)?;
 
         owner.require_auth();
 
         if duration_days == 0 || duration_days > 365 {
-            panic!("Invalid duration (1-365 days)");
+            return Err(SaveXError::InvalidDuration);
         }
 
+        let price = package_price(&env, &package_type)?;
+
+        let current_time = env.ledger().timestamp();
+
         // Check if user already has an active package
-        if let Some(existing_package) = env.storage()
-            .persistent()
-            .get::<PersistentDataKey, Package>(&PersistentDataKey::Package(owner.clone()))
-        {
-            if existing_package.is_active {
-                let current_time = env.ledger().timestamp();
-                if current_time < existing_package.end_date {
-                    panic!("Already have active package");
-                }
+        if let Ok(existing_package) = load_package(&env, &owner) {
+            if is_package_current(&env, &existing_package) {
+                return Err(SaveXError::PackageAlreadyActive);
             }
+
+            // A lapsed package has been fully used: settle its payment
+            release_package_revenue(&env, &existing_package, existing_package.prepaid)?;
+        }
+
+        // Members kept from a lapsed package must fit the new tier
+        if package_members(&env, &owner).len() > package_tier(&env, &package_type).seat_limit {
+            return Err(SaveXError::SeatLimitReached);
         }
 
-        let current_time = env.ledger().timestamp();
         let end_date = current_time + (duration_days as u64 * 86400); // 86400 seconds per day
+        let amount_paid = price.price_per_day * duration_days as i128;
+
+        if amount_paid > 0 {
+            token::Client::new(&env, &price.token).transfer(
+                &owner,
+                env.current_contract_address(),
+                &amount_paid,
+            );
+        }
 
         // Set discount rate based on package type
-        let discount_rate = match package_type {
-            PackageType::Family => 1500,    // 15% = 1500 basis points
-            PackageType::Business => 2000,  // 20% = 2000 basis points
-            PackageType::Premium => 2500,   // 25% = 2500 basis points
-        };
+        let discount_rate = package_tier(&env, &package_type).discount_rate;
 
         let package = Package {
             owner: owner.clone(),
-            package_type,
+            package_type: package_type.clone(),
             transfer_count: 0,
             total_volume: 0,
             discount_rate,
             start_date: current_time,
             end_date,
             is_active: true,
+            payment_token: price.token,
+            price_per_day: price.price_per_day,
+            prepaid: amount_paid,
+            period_start: current_time,
+            period_transfer_count: 0,
+            period_volume: 0,
         };
 
-        env.storage().persistent().set(&PersistentDataKey::Package(owner.clone()), &package);
-        env.storage().persistent().extend_ttl(&PersistentDataKey::Package(owner), INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+        store_package(&env, &package);
+        record_package_history(&env, &package, PackageAction::Subscribed, amount_paid, 0);
 
         // Increment package counter
         let counter: u64 = env.storage()
@@ -842,31 +3134,258 @@ impl SaveXContract {
             .get(&DataKey::PackageCounter)
             .unwrap_or(0);
         env.storage().instance().set(&DataKey::PackageCounter, &(counter + 1));
+
+        events::PackageSubscribed {
+            owner,
+            package_type,
+            discount_rate,
+            start_date: current_time,
+            end_date,
+            amount_paid,
+        }
+        .publish(&env);
+
+        Ok(())
     }
 
     /// Get package details
     pub fn get_package(env: Env, owner: Address) -> Option<Package> {
+        load_package(&env, &owner).ok()
+    }
+
+    /// Get the subscription timeline of an owner, oldest first
+    pub fn get_package_history(env: Env, owner: Address) -> Vec<PackageHistoryEntry> {
         env.storage()
             .persistent()
-            .get(&PersistentDataKey::Package(owner))
+            .get(&PersistentDataKey::PackageHistory(owner))
+            .unwrap_or_else(|| Vec::new(&env))
     }
 
-    /// Cancel package subscription
-    pub fn cancel_package(env: Env, owner: Address) {
+    /// Share an active package with `member`, up to the tier's seat limit
+    /// Members get the owner's discount and use the owner's quotas
+    /// Both the owner and the member must authorize; a member of a package that
+    /// is no longer current is free to join another
+    pub fn add_package_member(env: Env, owner: Address, member: Address) -> Result<(), SaveXError> {
+        require_not_paused(&env)?;
+
+        owner.require_auth();
+        member.require_auth();
+
+        let package = load_package(&env, &owner)?;
+        if !is_package_current(&env, &package) {
+            return Err(SaveXError::PackageNotActive);
+        }
+
+        if member == owner {
+            return Err(SaveXError::MemberAlreadyAdded);
+        }
+
+        let member_key = PersistentDataKey::PackageMember(member.clone());
+        if let Some(current_owner) = env.storage().persistent().get::<_, Address>(&member_key) {
+            match load_package(&env, &current_owner) {
+                Ok(current) if is_package_current(&env, &current) => {
+                    return Err(SaveXError::MemberAlreadyAdded);
+                }
+                _ => unlink_package_member(&env, &current_owner, &member)?,
+            }
+        }
+
+        let mut members = package_members(&env, &owner);
+        if members.len() >= package_tier(&env, &package.package_type).seat_limit {
+            return Err(SaveXError::SeatLimitReached);
+        }
+
+        members.push_back(member.clone());
+        store_package_members(&env, &owner, &members);
+
+        env.storage().persistent().set(&member_key, &owner);
+        env.storage().persistent().extend_ttl(&member_key, INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+
+        events::PackageMemberAdded { owner, member }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Remove `member` from the owner's package
+    pub fn remove_package_member(env: Env, owner: Address, member: Address) -> Result<(), SaveXError> {
         owner.require_auth();
 
-        let mut package: Package = env.storage()
+        unlink_package_member(&env, &owner, &member)
+    }
+
+    /// Leave the package `member` was added to
+    pub fn leave_package(env: Env, member: Address) -> Result<(), SaveXError> {
+        member.require_auth();
+
+        let owner: Address = env.storage()
             .persistent()
-            .get(&PersistentDataKey::Package(owner.clone()))
-            .expect("Package not found");
+            .get(&PersistentDataKey::PackageMember(member.clone()))
+            .ok_or(SaveXError::MemberNotFound)?;
+
+        unlink_package_member(&env, &owner, &member)
+    }
+
+    /// Get the members of an owner's package
+    pub fn get_package_members(env: Env, owner: Address) -> Vec<Address> {
+        package_members(&env, &owner)
+    }
+
+    /// Extend an active package by `extra_days` at its daily price
+    pub fn renew_package(env: Env, owner: Address, extra_days: u32) -> Result<(), SaveXError> {
+        require_not_paused(&env)?;
+
+        owner.require_auth();
+
+        let mut package = load_package(&env, &owner)?;
+        if !is_package_current(&env, &package) {
+            return Err(SaveXError::PackageNotActive);
+        }
+
+        // Remaining term after renewal is still capped at 365 days
+        let end_date = package.end_date + (extra_days as u64 * 86400);
+        if extra_days == 0 || end_date - env.ledger().timestamp() > 365 * 86400 {
+            return Err(SaveXError::InvalidDuration);
+        }
+
+        let amount_paid = package.price_per_day * extra_days as i128;
+        if amount_paid > 0 {
+            token::Client::new(&env, &package.payment_token).transfer(
+                &owner,
+                env.current_contract_address(),
+                &amount_paid,
+            );
+        }
+
+        package.end_date = end_date;
+        package.prepaid += amount_paid;
+        store_package(&env, &package);
+        record_package_history(&env, &package, PackageAction::Renewed, amount_paid, 0);
+
+        events::PackageRenewed {
+            owner,
+            package_type: package.package_type,
+            end_date,
+            amount_paid,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    /// Move an active package to a higher tier until its end date
+    /// Unused days of the current tier are credited against the new price
+    pub fn upgrade_package(env: Env, owner: Address, package_type: PackageType) -> Result<(), SaveXError> {
+        change_package(&env, &owner, package_type, PackageAction::Upgraded)
+    }
+
+    /// Move an active package to a lower tier until its end date
+    /// Credit left over after paying for the new tier is refunded
+    pub fn downgrade_package(env: Env, owner: Address, package_type: PackageType) -> Result<(), SaveXError> {
+        change_package(&env, &owner, package_type, PackageAction::Downgraded)
+    }
+
+    /// Cancel package subscription
+    /// Whole unused days are refunded; the used part goes to the treasury
+    pub fn cancel_package(env: Env, owner: Address) -> Result<(), SaveXError> {
+        owner.require_auth();
+
+        let mut package = load_package(&env, &owner)?;
 
         if !package.is_active {
-            panic!("Package not active");
+            return Err(SaveXError::PackageNotActive);
+        }
+
+        let (_, refund) = unused_package_credit(&env, &package);
+        if refund > 0 {
+            token::Client::new(&env, &package.payment_token).transfer(
+                &env.current_contract_address(),
+                &owner,
+                &refund,
+            );
         }
+        release_package_revenue(&env, &package, package.prepaid - refund)?;
 
         package.is_active = false;
-        env.storage().persistent().set(&PersistentDataKey::Package(owner.clone()), &package);
-        env.storage().persistent().extend_ttl(&PersistentDataKey::Package(owner), INSTANCE_LIFETIME_THRESHOLD, INSTANCE_BUMP_AMOUNT);
+        package.prepaid = 0;
+        store_package(&env, &package);
+        record_package_history(&env, &package, PackageAction::Cancelled, 0, refund);
+
+        // Members of a cancelled package are free to join another
+        clear_package_members(&env, &owner);
+
+        events::PackageCancelled {
+            owner,
+            package_type: package.package_type,
+            refund,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    /// Set the daily subscription price of a package type (admin only)
+    /// Paid packages require a treasury to receive the earned payments
+    pub fn set_package_price(
+        env: Env,
+        admin: Address,
+        package_type: PackageType,
+        token: Address,
+        price_per_day: i128,
+    ) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+
+        if price_per_day < 0 {
+            return Err(SaveXError::InvalidAmount);
+        }
+
+        require_supported_token(&env, &token)?;
+        if price_per_day > 0 {
+            treasury_address(&env)?;
+        }
+
+        let price = PackagePrice {
+            token: token.clone(),
+            price_per_day,
+        };
+        env.storage().instance().set(&DataKey::PackagePrice(package_type.clone()), &price);
+
+        events::PackagePriceUpdated {
+            admin,
+            package_type,
+            token,
+            price_per_day,
+        }
+        .publish(&env);
+
+        Ok(())
+    }
+
+    /// Set the discount and monthly quotas of a package type (admin only)
+    /// Active packages pick up the new tier on their next transfer
+    pub fn set_package_tier(
+        env: Env,
+        admin: Address,
+        package_type: PackageType,
+        tier: PackageTier,
+    ) -> Result<(), SaveXError> {
+        require_admin(&env, &admin)?;
+        validate_package_tier(&tier)?;
+
+        env.storage().instance().set(&DataKey::PackageTier(package_type.clone()), &tier);
+
+        events::PackageTierUpdated { admin, package_type, tier }.publish(&env);
+
+        Ok(())
+    }
+
+    /// Get the discount and monthly quotas of a package type
+    pub fn get_package_tier(env: Env, package_type: PackageType) -> PackageTier {
+        package_tier(&env, &package_type)
+    }
+
+    /// Get the daily subscription price of a package type
+    pub fn get_package_price(env: Env, package_type: PackageType) -> Result<PackagePrice, SaveXError> {
+        package_price(&env, &package_type)
     }
 
 
@@ -882,32 +3401,8 @@ impl SaveXContract {
         from_token: Address,
         to_token: Address,
         amount: i128,
-    ) -> i128 {
-        // Check if router and factory are configured
-        let has_router = env.storage().instance().has(&DataKey::RouterAddress);
-        let has_factory = env.storage().instance().has(&DataKey::FactoryAddress);
-
-        if !has_router || !has_factory {
-            panic!("Router or Factory not configured");
-        }
-
-        let router_address: Address = env.storage()
-            .instance()
-            .get(&DataKey::RouterAddress)
-            .unwrap();
-
-        let router = SoroswapRouterClient::new(&env, &router_address);
-
-        // Build path for query
-        let mut path = Vec::new(&env);
-        path.push_back(from_token);
-        path.push_back(to_token);
-
-        // Get amounts out from router (this queries actual pool reserves)
-        let amounts = router.get_amounts_out(&amount, &path);
-
-        // Return output amount (last element)
-        amounts.get(amounts.len() - 1).unwrap()
+    ) -> Result<i128, SaveXError> {
+        soroswap_quote(&env, &from_token, &to_token, amount)
     }
 
     /// Get quote from Stellar Classic DEX
@@ -940,7 +3435,7 @@ impl SaveXContract {
         from_token: Address,
         to_token: Address,
         amount: i128,
-    ) -> (u32, i128) {
+    ) -> Result<(u32, i128), SaveXError> {
         let stellar_quote = Self::get_stellar_dex_quote(
             env.clone(),
             from_token.clone(),
@@ -953,13 +3448,13 @@ impl SaveXContract {
             from_token,
             to_token,
             amount,
-        );
+        )?;
 
         // Return the better quote
         if stellar_quote > soroswap_quote {
-            (0, stellar_quote) // 0 = Stellar DEX
+            Ok((0, stellar_quote)) // 0 = Stellar DEX
         } else {
-            (1, soroswap_quote) // 1 = Soroswap
+            Ok((1, soroswap_quote)) // 1 = Soroswap
         }
     }
 
@@ -968,71 +3463,18 @@ impl SaveXContract {
     // ========================================================================
 
     /// Calculate fee for a transfer with package discount applied
+    /// `total` is the amount transfers deduct and send to the treasury,
+    /// in units of `token`
     pub fn calculate_fee(
         env: Env,
         from: Address,
+        token: Address,
         amount: i128,
         is_batch: bool,
         batch_size: u32,
-    ) -> FeeBreakdown {
-        // Default fee structure
-        let fee_structure = FeeStructure {
-            base_fee: 1000000,          // 0.1 XLM (1 XLM = 10,000,000 stroops)
-            percentage_fee: 50,          // 0.5% = 50 basis points
-            min_fee: 500000,            // 0.05 XLM minimum
-            max_fee: 100000000,         // 10 XLM maximum
-        };
-
-        let base_fee = calculate_base_fee(amount, &fee_structure);
-
-        // Apply batch discount (10% per additional transfer)
-        let batch_discount = if is_batch && batch_size > 1 {
-            let discount_percentage = (batch_size - 1) * 10; // 10% per extra transfer
-            let max_discount = 50; // Max 50% discount
-            let actual_discount = if discount_percentage > max_discount {
-                max_discount
-            } else {
-                discount_percentage
-            };
-            (base_fee * actual_discount as i128) / 100
-        } else {
-            0
-        };
-
-        // Apply package discount
-        let package_discount = if let Some(package) = env.storage()
-            .persistent()
-            .get::<PersistentDataKey, Package>(&PersistentDataKey::Package(from.clone()))
-        {
-            if package.is_active {
-                let current_time = env.ledger().timestamp();
-                if current_time <= package.end_date {
-                    // Apply package discount rate
-                    (base_fee * package.discount_rate as i128) / 10000
-                } else {
-                    0
-                }
-            } else {
-                0
-            }
-        } else {
-            0
-        };
-
-        let total_discount = batch_discount + package_discount;
-        let total_fee = base_fee - total_discount;
-        let final_fee = if total_fee < fee_structure.min_fee {
-            fee_structure.min_fee
-        } else {
-            total_fee
-        };
-
-        FeeBreakdown {
-            network_fee: base_fee / 2,      // Simulate network fee (half of base)
-            service_fee: base_fee / 2,      // Simulate service fee (half of base)
-            discount: total_discount,
-            total: final_fee,
-        }
+    ) -> Result<FeeBreakdown, SaveXError> {
+        let token_info = require_supported_token(&env, &token)?;
+        Ok(compute_fee(&env, &from, &token_info, amount, is_batch, batch_size))
     }
 
     /// Estimate savings from scheduling a transfer
@@ -1070,6 +3512,7 @@ impl SaveXContract {
     /// Execute a multi-hop token swap with transfer
     /// Automatically converts tokens through optimal DEX path
     /// Example: XLM → USDC → EUR stablecoin
+    #[allow(clippy::too_many_arguments)]
     pub fn transfer_with_swap(
         env: Env,
         from: Address,
@@ -1079,37 +3522,41 @@ impl SaveXContract {
         amount: i128,
         min_output_amount: i128,  // Slippage protection
         path: soroban_sdk::Vec<Address>,  // Intermediary tokens (empty for direct swap)
-    ) -> u64 {
-        if is_paused(&env) {
-            panic!("Contract is paused");
-        }
+    ) -> Result<u64, SaveXError> {
+        require_not_paused(&env)?;
 
         from.require_auth();
 
         if amount <= 0 {
-            panic!("Invalid amount");
+            return Err(SaveXError::InvalidAmount);
         }
 
         if min_output_amount <= 0 {
-            panic!("Invalid minimum output amount");
+            return Err(SaveXError::InvalidAmount);
         }
 
-        let current_time = env.ledger().timestamp();
+        let from_info = require_supported_token(&env, &from_token)?;
+        require_supported_token(&env, &to_token)?;
+
+        // Fee is charged in the input token before swapping
+        let fee = collect_fee(&env, &from, &from_info, amount, false, 1)?;
+        let swap_amount = amount - fee;
+        record_package_usage(&env, &from, amount);
 
         // Transfer input tokens to contract
         token::Client::new(&env, &from_token).transfer(
             &from,
-            &env.current_contract_address(),
-            &amount
+            env.current_contract_address(),
+            &swap_amount
         );
 
         // Execute swap through path
-        let output_amount = if path.len() == 0 {
+        let output_amount = if path.is_empty() {
             // Direct swap: from_token → to_token
-            execute_swap(&env, &from_token, &to_token, amount, min_output_amount)
+            execute_swap(&env, &from_token, &to_token, swap_amount, min_output_amount)?
         } else {
             // Multi-hop swap: from_token → path[0] → path[1] → ... → to_token
-            execute_multi_hop_swap(&env, &from_token, &to_token, amount, min_output_amount, &path)
+            execute_multi_hop_swap(&env, &from_token, &to_token, swap_amount, min_output_amount, &path)?
         };
 
         // Transfer output tokens to recipient
@@ -1119,27 +3566,12 @@ impl SaveXContract {
             &output_amount
         );
 
-        // Store transfer record
-        let transfer_id = next_transfer_id(&env);
-        let transfer = Transfer {
-            id: transfer_id,
-            from: from.clone(),
-            to: to.clone(),
-            token: to_token.clone(),  // Final token received
-            amount: output_amount,
-            transfer_type: TransferType::Immediate,
-            has_time_bound: false,
-            time_bound_kind: TimeBoundKind::After,
-            time_bound_timestamp: 0,
-            status: TransferStatus::Completed,
-            created_at: current_time,
-            has_rate_lock: false,
-            rate_lock_id: 0,
-        };
-
-        store_transfer(&env, &transfer);
+        // Store transfer record (token is the final token received)
+        let transfer = new_transfer(&env, &from, &to, &to_token, output_amount, TransferType::Immediate, 0);
+        save_new_transfer(&env, &transfer);
+        record_fee_receipt(&env, transfer.id, &from, &from_token, amount, fee);
 
-        transfer_id
+        Ok(transfer.id)
     }
 
     /// Get optimal swap path between two tokens
@@ -1175,7 +3607,7 @@ impl SaveXContract {
         from_token: Address,
         to_token: Address,
         amount: i128,
-    ) -> i128 {
+    ) -> Result<i128, SaveXError> {
         // Query actual exchange rate from liquidity pool
         get_exchange_rate(&env, &from_token, &to_token, amount)
     }
@@ -1198,20 +3630,17 @@ impl SaveXContract {
 /// * `min_output` - Minimum output tokens (slippage protection)
 ///
 /// # Returns
-/// Actual amount of output tokens received
+/// Actual amount of output tokens received, or `RouterNotConfigured` /
+/// `FactoryNotConfigured` if the Soroswap addresses have not been set
 fn execute_swap(
     env: &Env,
     from_token: &Address,
     to_token: &Address,
     amount: i128,
     min_output: i128,
-) -> i128 {
+) -> Result<i128, SaveXError> {
     // Get configured Soroswap Router address
-    let router_address: Address = env
-        .storage()
-        .instance()
-        .get(&DataKey::RouterAddress)
-        .expect("Router address not configured. Call set_router_address() first.");
+    let router_address = router_address(env)?;
 
     // Create Soroswap Router client
     let router = SoroswapRouterClient::new(env, &router_address);
@@ -1224,8 +3653,22 @@ fn execute_swap(
     // Set deadline: 5 minutes from now (300 seconds)
     let deadline = env.ledger().timestamp() + 300;
 
+    // The router moves the input from this contract into the pair, which is a
+    // nested call and so needs explicit authorization from the contract
+    let pair = SoroswapFactoryClient::new(env, &factory_address(env)?).get_pair(from_token, to_token);
+    env.authorize_as_current_contract(vec![
+        env,
+        InvokerContractAuthEntry::Contract(SubContractInvocation {
+            context: ContractContext {
+                contract: from_token.clone(),
+                fn_name: Symbol::new(env, "transfer"),
+                args: (env.current_contract_address(), pair, amount).into_val(env),
+            },
+            sub_invocations: Vec::new(env),
+        }),
+    ]);
+
     // Execute swap via Soroswap Router
-    // Router will handle token approvals and execute through liquidity pools
     let amounts = router.swap_exact_tokens_for_tokens(
         &amount,
         &min_output,
@@ -1236,7 +3679,7 @@ fn execute_swap(
 
     // Return actual output amount (last element in amounts vector)
     // amounts[0] = input amount, amounts[1] = output amount for direct swap
-    amounts.get(amounts.len() - 1).unwrap()
+    Ok(amounts.get(amounts.len() - 1).unwrap())
 }
 
 /// Execute multi-hop swap through intermediary tokens
@@ -1247,7 +3690,7 @@ fn execute_multi_hop_swap(
     initial_amount: i128,
     min_final_output: i128,
     path: &soroban_sdk::Vec<Address>,
-) -> i128 {
+) -> Result<i128, SaveXError> {
     let mut current_amount = initial_amount;
     let mut current_token = from_token.clone();
 
@@ -1255,14 +3698,37 @@ fn execute_multi_hop_swap(
     for i in 0..path.len() {
         let next_token = path.get(i).unwrap();
         let min_output = 0;  // Intermediate swaps don't need slippage check
-        current_amount = execute_swap(env, &current_token, &next_token, current_amount, min_output);
+        current_amount = execute_swap(env, &current_token, &next_token, current_amount, min_output)?;
         current_token = next_token;
     }
 
     // Final swap to destination token
-    let final_output = execute_swap(env, &current_token, to_token, current_amount, min_final_output);
+    execute_swap(env, &current_token, to_token, current_amount, min_final_output)
+}
+
+/// Get the Soroswap output for swapping `amount` of `from_token` into `to_token`
+fn soroswap_quote(
+    env: &Env,
+    from_token: &Address,
+    to_token: &Address,
+    amount: i128,
+) -> Result<i128, SaveXError> {
+    // Check if router and factory are configured
+    let router_address = router_address(env)?;
+    factory_address(env)?;
+
+    let router = SoroswapRouterClient::new(env, &router_address);
+
+    // Build path for query
+    let mut path = Vec::new(env);
+    path.push_back(from_token.clone());
+    path.push_back(to_token.clone());
+
+    // Get amounts out from router (this queries actual pool reserves)
+    let amounts = router.get_amounts_out(&amount, &path);
 
-    final_output
+    // Return output amount (last element)
+    Ok(amounts.get(amounts.len() - 1).unwrap())
 }
 
 /// Get estimated exchange rate for a swap (read-only preview)
@@ -1281,24 +3747,15 @@ fn get_exchange_rate(
     from_token: &Address,
     to_token: &Address,
     amount: i128,
-) -> i128 {
+) -> Result<i128, SaveXError> {
     // Same token check
     if from_token == to_token {
-        return amount;
-    }
-
-    // Check if router is configured
-    let has_router = env.storage().instance().has(&DataKey::RouterAddress);
-    let has_factory = env.storage().instance().has(&DataKey::FactoryAddress);
-
-    if !has_router || !has_factory {
-        panic!("Router and Factory must be configured for exchange rate queries");
+        return Ok(amount);
     }
 
-    let router_address: Address = env.storage()
-        .instance()
-        .get(&DataKey::RouterAddress)
-        .unwrap();
+    // Router and Factory must be configured for exchange rate queries
+    let router_address = router_address(env)?;
+    factory_address(env)?;
 
     let router = SoroswapRouterClient::new(env, &router_address);
 
@@ -1311,7 +3768,8 @@ fn get_exchange_rate(
     let amounts = router.get_amounts_out(&amount, &path);
 
     // Return output amount
-    amounts.get(amounts.len() - 1).unwrap()
+    Ok(amounts.get(amounts.len() - 1).unwrap())
 }
 
+mod mock;
 mod test;
@@ -0,0 +1,153 @@
+//! Mock contracts used by the tests in place of Soroswap
+
+#![cfg(test)]
+
+use soroban_sdk::{contract, contractimpl, contracttype, token, vec, Address, Bytes, Env, Vec};
+
+use crate::FlashBorrowerClient;
+
+#[derive(Clone)]
+#[contracttype]
+enum MockDataKey {
+    Rate,
+    Pair,
+    PairFor(Address, Address),
+    FeeBps,
+    Token0,
+    Token1,
+    Reserves,
+    Overstatement,
+}
+
+/// Router that swaps at a fixed rate (7 decimals) from its own balances
+/// Like Soroswap, it pulls the input from `to` and pays the output to `to`;
+/// the router itself plays the role of the pair
+#[contract]
+pub struct MockRouter;
+
+#[contractimpl]
+impl MockRouter {
+    pub fn set_rate(env: Env, rate: i128) {
+        env.storage().instance().set(&MockDataKey::Rate, &rate);
+    }
+
+    /// Report `amount` more output from swaps than is actually paid
+    pub fn set_overstatement(env: Env, amount: i128) {
+        env.storage().instance().set(&MockDataKey::Overstatement, &amount);
+    }
+
+    pub fn swap_exact_tokens_for_tokens(
+        env: Env,
+        amount_in: i128,
+        amount_out_min: i128,
+        path: Vec<Address>,
+        to: Address,
+        _deadline: u64,
+    ) -> Vec<i128> {
+        to.require_auth();
+
+        let amounts = Self::get_amounts_out(env.clone(), amount_in, path.clone());
+        let amount_out = amounts.get(amounts.len() - 1).unwrap();
+        if amount_out < amount_out_min {
+            panic!("insufficient output amount");
+        }
+
+        let token_in = path.get(0).unwrap();
+        let token_out = path.get(path.len() - 1).unwrap();
+        token::Client::new(&env, &token_in).transfer(&to, env.current_contract_address(), &amount_in);
+        token::Client::new(&env, &token_out).transfer(&env.current_contract_address(), &to, &amount_out);
+
+        let overstatement: i128 = env.storage().instance().get(&MockDataKey::Overstatement).unwrap_or(0);
+        let mut reported = amounts.clone();
+        reported.set(amounts.len() - 1, amount_out + overstatement);
+        reported
+    }
+
+    pub fn get_amounts_out(env: Env, amount_in: i128, path: Vec<Address>) -> Vec<i128> {
+        let rate: i128 = env.storage().instance().get(&MockDataKey::Rate).unwrap_or(10_000_000);
+
+        let mut amounts = vec![&env, amount_in];
+        let mut amount = amount_in;
+        for _ in 1..path.len() {
+            amount = (amount * rate) / 10_000_000;
+            amounts.push_back(amount);
+        }
+        amounts
+    }
+}
+
+/// Factory that reports the same pair for every token pair unless one was set
+/// for that pair of tokens
+#[contract]
+pub struct MockFactory;
+
+#[contractimpl]
+impl MockFactory {
+    pub fn set_pair(env: Env, pair: Address) {
+        env.storage().instance().set(&MockDataKey::Pair, &pair);
+    }
+
+    pub fn set_pair_for(env: Env, token_a: Address, token_b: Address, pair: Address) {
+        env.storage().instance().set(&MockDataKey::PairFor(token_a.clone(), token_b.clone()), &pair);
+        env.storage().instance().set(&MockDataKey::PairFor(token_b, token_a), &pair);
+    }
+
+    pub fn get_pair(env: Env, token_a: Address, token_b: Address) -> Address {
+        env.storage()
+            .instance()
+            .get(&MockDataKey::PairFor(token_a, token_b))
+            .unwrap_or_else(|| env.storage().instance().get(&MockDataKey::Pair).unwrap())
+    }
+}
+
+/// Pair that only reports its tokens and reserves
+#[contract]
+pub struct MockPair;
+
+#[contractimpl]
+impl MockPair {
+    pub fn set_reserves(env: Env, token_0: Address, token_1: Address, reserve_0: i128, reserve_1: i128) {
+        env.storage().instance().set(&MockDataKey::Token0, &token_0);
+        env.storage().instance().set(&MockDataKey::Token1, &token_1);
+        env.storage().instance().set(&MockDataKey::Reserves, &(reserve_0, reserve_1));
+    }
+
+    pub fn get_reserves(env: Env) -> (i128, i128, u64) {
+        let (reserve_0, reserve_1): (i128, i128) = env.storage().instance().get(&MockDataKey::Reserves).unwrap();
+        (reserve_0, reserve_1, env.ledger().timestamp())
+    }
+
+    pub fn token_0(env: Env) -> Address {
+        env.storage().instance().get(&MockDataKey::Token0).unwrap()
+    }
+
+    pub fn token_1(env: Env) -> Address {
+        env.storage().instance().get(&MockDataKey::Token1).unwrap()
+    }
+}
+
+/// Flash lender that lends from its own balances for a fee in basis points
+#[contract]
+pub struct MockFlashLender;
+
+#[contractimpl]
+impl MockFlashLender {
+    pub fn set_fee_bps(env: Env, fee_bps: i128) {
+        env.storage().instance().set(&MockDataKey::FeeBps, &fee_bps);
+    }
+
+    pub fn flash_loan(env: Env, receiver: Address, token: Address, amount: i128, data: Bytes) {
+        let fee_bps: i128 = env.storage().instance().get(&MockDataKey::FeeBps).unwrap_or(0);
+        let fee = amount * fee_bps / 10_000;
+
+        let token_client = token::Client::new(&env, &token);
+        let balance_before = token_client.balance(&env.current_contract_address());
+
+        token_client.transfer(&env.current_contract_address(), &receiver, &amount);
+        FlashBorrowerClient::new(&env, &receiver).on_flash_loan(&token, &amount, &fee, &data);
+
+        if token_client.balance(&env.current_contract_address()) < balance_before + fee {
+            panic!("flash loan not repaid");
+        }
+    }
+}
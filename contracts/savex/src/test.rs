@@ -1,9 +1,16 @@
 #![cfg(test)]
 
 use super::*;
+use crate::mock::{
+    MockFactory, MockFactoryClient, MockFlashLender, MockFlashLenderClient, MockPair, MockPairClient, MockRouter,
+    MockRouterClient,
+};
 use soroban_sdk::{
-    testutils::{Address as _, Ledger, LedgerInfo},
-    token, Address, Env,
+    events::Event,
+    testutils::{Address as _, Events as _, Ledger, LedgerInfo},
+    token, vec,
+    xdr::ToXdr,
+    Address, Bytes, BytesN, Env, String, Val,
 };
 
 // Helper function to create a token contract for testing
@@ -15,6 +22,90 @@ fn create_token_contract<'a>(env: &Env, admin: &Address) -> (token::Client<'a>,
     )
 }
 
+// Helper function to register a 7-decimal token with the contract
+fn register_token(env: &Env, client: &SaveXContractClient, admin: &Address, token: &Address) {
+    client.register_token(admin, token, &String::from_str(env, "TEST"), &7);
+}
+
+// Helper function to price every package type in a new registered token
+fn setup_package_prices<'a>(
+    env: &Env,
+    client: &SaveXContractClient,
+    admin: &Address,
+    price_per_day: i128,
+) -> (token::Client<'a>, token::StellarAssetClient<'a>) {
+    let (token, token_admin) = create_token_contract(env, admin);
+    register_token(env, client, admin, &token.address);
+    for package_type in [PackageType::Family, PackageType::Business, PackageType::Premium] {
+        client.set_package_price(admin, &package_type, &token.address, &price_per_day);
+    }
+    (token, token_admin)
+}
+
+// Helper function to point the contract at a mock router and factory
+fn setup_mock_router<'a>(env: &Env, client: &SaveXContractClient, admin: &Address) -> MockRouterClient<'a> {
+    let router = MockRouterClient::new(env, &env.register(MockRouter, ()));
+    let factory = MockFactoryClient::new(env, &env.register(MockFactory, ()));
+    factory.set_pair(&router.address);
+
+    client.set_router_address(admin, &router.address);
+    client.set_factory_address(admin, &factory.address);
+    router
+}
+
+// Helper function to create two registered tokens and a mock router that
+// swaps the first into the second at `rate` (7 decimals)
+fn setup_swap_pair<'a>(
+    env: &Env,
+    client: &SaveXContractClient,
+    admin: &Address,
+    rate: i128,
+) -> (token::Client<'a>, token::StellarAssetClient<'a>, token::Client<'a>, MockRouterClient<'a>) {
+    let (token_a, token_a_admin) = create_token_contract(env, admin);
+    let (token_b, token_b_admin) = create_token_contract(env, admin);
+    register_token(env, client, admin, &token_a.address);
+    register_token(env, client, admin, &token_b.address);
+
+    let router = setup_mock_router(env, client, admin);
+    router.set_rate(&rate);
+    token_b_admin.mint(&router.address, &1_000_000_000);
+    if rate > 0 {
+        client.set_reference_rate(admin, &token_a.address, &token_b.address, &rate);
+    }
+
+    (token_a, token_a_admin, token_b, router)
+}
+
+// Helper function to deploy an ArbitrageBot sharing SaveX's mock router and
+// factory, with `executor` allowed to trade
+fn setup_arbitrage_bot<'a>(
+    env: &Env,
+    client: &SaveXContractClient,
+    admin: &Address,
+    executor: &Address,
+) -> ArbitrageBotClient<'a> {
+    let bot = ArbitrageBotClient::new(env, &env.register(ArbitrageBot, ()));
+    bot.arb_initialize(admin, &client.get_router_address(), &client.get_factory_address());
+    bot.arb_set_executor(admin, executor, &true);
+    bot
+}
+
+// Helper function to collect (topics, data) of events published by one contract
+fn contract_events(env: &Env, contract_id: &Address) -> soroban_sdk::Vec<(soroban_sdk::Vec<Val>, Val)> {
+    let mut events = soroban_sdk::Vec::new(env);
+    for (contract, topics, data) in env.events().all().iter() {
+        if contract == *contract_id {
+            events.push_back((topics, data));
+        }
+    }
+    events
+}
+
+// Helper function to turn an expected event into the (topics, data) form
+fn event_entry(env: &Env, event: &impl Event) -> (soroban_sdk::Vec<Val>, Val) {
+    (event.topics(env), event.data(env))
+}
+
 #[test]
 fn test_initialize() {
     let env = Env::default();
@@ -32,7 +123,6 @@ fn test_initialize() {
 }
 
 #[test]
-#[should_panic(expected = "Already initialized")]
 fn test_double_initialize() {
     let env = Env::default();
     env.mock_all_auths();
@@ -43,7 +133,7 @@ fn test_double_initialize() {
     let admin = Address::generate(&env);
 
     client.initialize(&admin);
-    client.initialize(&admin); // Should panic
+    assert_eq!(client.try_initialize(&admin), Err(Ok(SaveXError::AlreadyInitialized)));
 }
 
 #[test]
@@ -62,6 +152,7 @@ fn test_immediate_transfer() {
 
     // Create token
     let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
     token_admin.mint(&user1, &1000);
 
     // Perform immediate transfer
@@ -95,6 +186,7 @@ fn test_scheduled_transfer() {
 
     // Create token
     let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
     token_admin.mint(&user1, &1000);
 
     // Set initial ledger timestamp
@@ -173,6 +265,7 @@ fn test_cancel_scheduled_transfer() {
 
     // Create token
     let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
     token_admin.mint(&user1, &1000);
 
     // Set initial ledger timestamp
@@ -219,11 +312,13 @@ fn test_rate_lock() {
 
     let admin = Address::generate(&env);
     let user = Address::generate(&env);
-    let token1 = Address::generate(&env);
-    let token2 = Address::generate(&env);
 
     client.initialize(&admin);
 
+    // Pool rate: 1.5 (represented as 15000000 with 7 decimals)
+    let (token1, token1_admin, token2, _router) = setup_swap_pair(&env, &client, &admin, 15000000);
+    token1_admin.mint(&user, &20000);
+
     // Set initial ledger timestamp
     env.ledger().set(LedgerInfo {
         timestamp: 1000,
@@ -236,28 +331,31 @@ fn test_rate_lock() {
         max_entry_ttl: 3110400,
     });
 
-    // Lock rate: 1.5 (represented as 15000000 with 7 decimals)
-    let locked_rate = 15000000i128;
-    let amount = 1000i128;
+    let amount = 10000i128;
     let duration = 3600u64; // 1 hour
 
     let lock_id = client.lock_rate(
         &user,
-        &token1,
-        &token2,
-        &locked_rate,
+        &token1.address,
+        &token2.address,
         &amount,
         &duration
     );
 
     assert_eq!(lock_id, 1);
 
-    // Check rate lock
+    // Check rate lock: priced from the pool, 0.12% premium for one hour
     let rate_lock = client.get_rate_lock(&lock_id);
-    assert_eq!(rate_lock.locked_rate, locked_rate);
+    assert_eq!(rate_lock.locked_rate, 15000000);
     assert_eq!(rate_lock.amount, amount);
-    assert_eq!(rate_lock.is_active, true);
+    assert_eq!(rate_lock.premium, 12);
+    assert!(rate_lock.is_active);
     assert_eq!(rate_lock.expiry, 1000 + 3600);
+
+    // Amount and premium are escrowed; the premium funds the hedge reserve
+    assert_eq!(token1.balance(&user), 20000 - 10012);
+    assert_eq!(token1.balance(&contract_id), 10012);
+    assert_eq!(client.get_hedge_reserve(&token1.address), 12);
 }
 
 #[test]
@@ -270,11 +368,12 @@ fn test_cancel_rate_lock() {
 
     let admin = Address::generate(&env);
     let user = Address::generate(&env);
-    let token1 = Address::generate(&env);
-    let token2 = Address::generate(&env);
 
     client.initialize(&admin);
 
+    let (token1, token1_admin, token2, _router) = setup_swap_pair(&env, &client, &admin, 15000000);
+    token1_admin.mint(&user, &20000);
+
     // Set initial ledger timestamp
     env.ledger().set(LedgerInfo {
         timestamp: 1000,
@@ -290,19 +389,20 @@ fn test_cancel_rate_lock() {
     // Lock rate
     let lock_id = client.lock_rate(
         &user,
-        &token1,
-        &token2,
-        &15000000,
-        &1000,
+        &token1.address,
+        &token2.address,
+        &10000,
         &3600
     );
 
     // Cancel rate lock
     client.cancel_rate_lock(&user, &lock_id);
 
-    // Check rate lock status
+    // Check rate lock status and escrow refund (premium is kept)
     let rate_lock = client.get_rate_lock(&lock_id);
-    assert_eq!(rate_lock.is_active, false);
+    assert!(!rate_lock.is_active);
+    assert_eq!(rate_lock.remaining_amount, 0);
+    assert_eq!(token1.balance(&user), 20000 - 12);
 }
 
 #[test]
@@ -340,6 +440,7 @@ fn test_split_transfer() {
 
     // Create token
     let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
     token_admin.mint(&user1, &1000);
 
     // Set initial ledger timestamp
@@ -414,6 +515,7 @@ fn test_batch_transfer() {
 
     // Create token
     let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
     token_admin.mint(&sender, &1000);
 
     // Create batch transfer
@@ -464,6 +566,7 @@ fn test_package_subscription() {
     let user = Address::generate(&env);
 
     client.initialize(&admin);
+    setup_package_prices(&env, &client, &admin, 0);
 
     // Set initial ledger timestamp
     env.ledger().set(LedgerInfo {
@@ -484,7 +587,7 @@ fn test_package_subscription() {
     let package = client.get_package(&user).unwrap();
     assert_eq!(package.package_type, PackageType::Family);
     assert_eq!(package.discount_rate, 1500); // 15%
-    assert_eq!(package.is_active, true);
+    assert!(package.is_active);
     assert_eq!(package.end_date, 1000 + (30 * 86400));
 }
 
@@ -500,6 +603,7 @@ fn test_cancel_package() {
     let user = Address::generate(&env);
 
     client.initialize(&admin);
+    setup_package_prices(&env, &client, &admin, 0);
 
     // Set initial ledger timestamp
     env.ledger().set(LedgerInfo {
@@ -519,7 +623,7 @@ fn test_cancel_package() {
 
     // Check package is inactive
     let package = client.get_package(&user).unwrap();
-    assert_eq!(package.is_active, false);
+    assert!(!package.is_active);
 }
 
 #[test]
@@ -534,9 +638,13 @@ fn test_fee_calculation() {
     let user = Address::generate(&env);
 
     client.initialize(&admin);
+    setup_package_prices(&env, &client, &admin, 0);
+
+    let (token, _token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
 
     // Calculate fee without package
-    let fee1 = client.calculate_fee(&user, &10000000, &false, &1);
+    let fee1 = client.calculate_fee(&user, &token.address, &10000000, &false, &1);
     assert!(fee1.total > 0);
 
     // Set timestamp
@@ -555,7 +663,7 @@ fn test_fee_calculation() {
     client.subscribe_package(&user, &PackageType::Premium, &30);
 
     // Calculate fee with package (should be lower)
-    let fee2 = client.calculate_fee(&user, &10000000, &false, &1);
+    let fee2 = client.calculate_fee(&user, &token.address, &10000000, &false, &1);
     assert!(fee2.total < fee1.total);
     assert!(fee2.discount > 0);
 }
@@ -573,11 +681,14 @@ fn test_batch_fee_discount() {
 
     client.initialize(&admin);
 
+    let (token, _token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
+
     // Single transfer fee
-    let fee_single = client.calculate_fee(&user, &10000000, &false, &1);
+    let fee_single = client.calculate_fee(&user, &token.address, &10000000, &false, &1);
 
     // Batch transfer fee (5 transfers)
-    let fee_batch = client.calculate_fee(&user, &10000000, &true, &5);
+    let fee_batch = client.calculate_fee(&user, &token.address, &10000000, &true, &5);
 
     // Batch should have discount
     assert!(fee_batch.discount > 0);
@@ -630,7 +741,9 @@ fn test_transfer_with_swap() {
 
     // Create two different tokens (XLM and USDC)
     let (token_xlm, token_xlm_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token_xlm.address);
     let (token_usdc, _token_usdc_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token_usdc.address);
 
     // Mint XLM to user1
     token_xlm_admin.mint(&user1, &1000);
@@ -672,8 +785,11 @@ fn test_multi_hop_swap() {
 
     // Create three tokens: XLM → USDC → EUR
     let (token_xlm, token_xlm_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token_xlm.address);
     let (token_usdc, _token_usdc_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token_usdc.address);
     let (token_eur, _token_eur_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token_eur.address);
 
     token_xlm_admin.mint(&user1, &1000);
 
@@ -743,12 +859,8 @@ fn test_estimate_swap_output() {
 }
 
 #[test]
-#[ignore] // Requires Soroswap Router on testnet - enable after configuring Router address
-#[should_panic(expected = "Router address not configured")]
-fn test_swap_slippage_protection() {
-    // NOTE: This test verifies slippage protection via Soroswap Router
-    // Router's swap_exact_tokens_for_tokens enforces min_output automatically
-    // Test expects panic because Router is not configured in unit test environment
+fn test_swap_requires_router() {
+    // Without a configured router the swap fails before reaching a pool
 
     let env = Env::default();
     env.mock_all_auths();
@@ -763,14 +875,17 @@ fn test_swap_slippage_protection() {
     client.initialize(&admin);
 
     let (token1, token1_admin) = create_token_contract(&env, &admin);
+
+    register_token(&env, &client, &admin, &token1.address);
     let (token2, _token2_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token2.address);
 
     token1_admin.mint(&user1, &1000);
 
     let path = soroban_sdk::Vec::new(&env);
 
-    // Try to swap with unrealistic minimum output - should panic with slippage error
-    client.transfer_with_swap(
+    // Try to swap with unrealistic minimum output - should fail before reaching a pool
+    let result = client.try_transfer_with_swap(
         &user1,
         &user2,
         &token1.address,
@@ -779,4 +894,2897 @@ fn test_swap_slippage_protection() {
         &5000,  // Expecting 5000 output from 1000 input = impossible
         &path
     );
+    assert_eq!(result, Err(Ok(SaveXError::RouterNotConfigured)));
+
+    // Input tokens are not taken when the swap fails
+    assert_eq!(token1.balance(&user1), 1000);
+}
+
+#[test]
+fn test_swap_slippage_protection() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+
+    client.initialize(&admin);
+    let (token1, token1_admin, token2, _router) = setup_swap_pair(&env, &client, &admin, 15_000_000);
+    token1_admin.mint(&user1, &2000);
+
+    let path = soroban_sdk::Vec::new(&env);
+
+    // The pool pays at most 1500 for 1000; asking for more makes the router revert
+    assert!(client
+        .try_transfer_with_swap(&user1, &user2, &token1.address, &token2.address, &1000, &1501, &path)
+        .is_err());
+    assert_eq!(token1.balance(&user1), 2000);
+    assert_eq!(token2.balance(&user2), 0);
+
+    // A minimum the quoted output meets goes through
+    let transfer_id = client.transfer_with_swap(&user1, &user2, &token1.address, &token2.address, &1000, &1400, &path);
+    let received = token2.balance(&user2);
+    assert!(received >= 1400);
+    assert_eq!(client.get_transfer(&transfer_id).amount, received);
+    assert_eq!(token1.balance(&user1), 1000);
+}
+
+#[test]
+fn test_admin_errors() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let stranger = Address::generate(&env);
+
+    // Admin calls before initialization
+    assert_eq!(client.try_pause(&admin), Err(Ok(SaveXError::NotInitialized)));
+
+    client.initialize(&admin);
+
+    assert_eq!(client.try_pause(&stranger), Err(Ok(SaveXError::NotAuthorized)));
+    assert_eq!(
+        client.try_set_router_address(&stranger, &stranger),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+    assert_eq!(client.try_get_router_address(), Err(Ok(SaveXError::RouterNotConfigured)));
+    assert_eq!(client.try_get_factory_address(), Err(Ok(SaveXError::FactoryNotConfigured)));
+}
+
+#[test]
+fn test_paused_contract_rejects_transfers() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&user1, &1000);
+
+    client.pause(&admin);
+
+    assert_eq!(
+        client.try_transfer_immediate(&user1, &user2, &token.address, &100),
+        Err(Ok(SaveXError::ContractPaused))
+    );
+
+    client.unpause(&admin);
+    client.transfer_immediate(&user1, &user2, &token.address, &100);
+    assert_eq!(token.balance(&user2), 100);
+}
+
+#[test]
+fn test_transfer_errors() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&user1, &1000);
+
+    env.ledger().set_timestamp(1000);
+
+    assert_eq!(
+        client.try_transfer_immediate(&user1, &user2, &token.address, &0),
+        Err(Ok(SaveXError::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_transfer_scheduled(&user1, &user2, &token.address, &100, &1000),
+        Err(Ok(SaveXError::InvalidTimestamp))
+    );
+    assert_eq!(
+        client.try_transfer_split(&user1, &user2, &token.address, &100, &101, &2000),
+        Err(Ok(SaveXError::InvalidPercentage))
+    );
+    assert_eq!(client.try_get_transfer(&42).err(), Some(Ok(SaveXError::TransferNotFound)));
+
+    let transfer_id = client.transfer_scheduled(&user1, &user2, &token.address, &100, &2000);
+
+    // Too early to execute
+    assert_eq!(
+        client.try_execute_scheduled_transfer(&transfer_id),
+        Err(Ok(SaveXError::TimeBoundNotMet))
+    );
+
+    // Only the sender may cancel
+    assert_eq!(
+        client.try_cancel_scheduled_transfer(&user2, &transfer_id),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+
+    client.cancel_scheduled_transfer(&user1, &transfer_id);
+
+    // Cancelled transfers can no longer be executed or cancelled
+    env.ledger().set_timestamp(2001);
+    assert_eq!(
+        client.try_execute_scheduled_transfer(&transfer_id),
+        Err(Ok(SaveXError::TransferNotPending))
+    );
+    assert_eq!(
+        client.try_cancel_scheduled_transfer(&user1, &transfer_id),
+        Err(Ok(SaveXError::TransferNotPending))
+    );
+}
+
+#[test]
+fn test_batch_transfer_errors() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let sender = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&sender, &100_000);
+
+    let mut recipients = soroban_sdk::Vec::new(&env);
+    let mut amounts = soroban_sdk::Vec::new(&env);
+
+    assert_eq!(
+        client.try_transfer_batch(&sender, &recipients, &token.address, &amounts),
+        Err(Ok(SaveXError::EmptyBatch))
+    );
+
+    recipients.push_back(Address::generate(&env));
+    assert_eq!(
+        client.try_transfer_batch(&sender, &recipients, &token.address, &amounts),
+        Err(Ok(SaveXError::LengthMismatch))
+    );
+
+    amounts.push_back(0);
+    assert_eq!(
+        client.try_transfer_batch(&sender, &recipients, &token.address, &amounts),
+        Err(Ok(SaveXError::InvalidAmount))
+    );
+
+    for _ in 0..50 {
+        recipients.push_back(Address::generate(&env));
+        amounts.push_back(10);
+    }
+    assert_eq!(
+        client.try_transfer_batch(&sender, &recipients, &token.address, &amounts),
+        Err(Ok(SaveXError::BatchTooLarge))
+    );
+}
+
+#[test]
+fn test_rate_lock_errors() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+    let other = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let unknown = Address::generate(&env);
+    assert_eq!(
+        client.try_lock_rate(&user, &unknown, &unknown, &1000, &3600),
+        Err(Ok(SaveXError::TokenNotSupported))
+    );
+
+    let (token1, token1_admin, token2, router) = setup_swap_pair(&env, &client, &admin, 0);
+    let (token1, token2) = (token1.address, token2.address);
+    token1_admin.mint(&user, &10000);
+
+    env.ledger().set_timestamp(1000);
+
+    // Pool quotes nothing for this amount
+    assert_eq!(
+        client.try_lock_rate(&user, &token1, &token2, &1000, &3600),
+        Err(Ok(SaveXError::InvalidRate))
+    );
+    router.set_rate(&15000000);
+    assert_eq!(
+        client.try_lock_rate(&user, &token1, &token2, &1000, &86401),
+        Err(Ok(SaveXError::DurationTooLong))
+    );
+
+    // Locks need a reference rate and must stay within 2% of it
+    assert_eq!(
+        client.try_lock_rate(&user, &token1, &token2, &1000, &3600),
+        Err(Ok(SaveXError::InvalidRate))
+    );
+    assert_eq!(
+        client.try_set_reference_rate(&other, &token1, &token2, &15000000),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+    assert_eq!(
+        client.try_set_reference_rate(&admin, &token1, &token2, &0),
+        Err(Ok(SaveXError::InvalidRate))
+    );
+    client.set_reference_rate(&admin, &token1, &token2, &14_700_000);
+    assert_eq!(client.get_reference_rate(&token1, &token2), Some(14_700_000));
+    assert_eq!(client.get_reference_rate(&token2, &token1), None);
+    assert_eq!(
+        client.try_lock_rate(&user, &token1, &token2, &1000, &3600),
+        Err(Ok(SaveXError::SlippageExceeded))
+    );
+    // A reference rate only covers its own direction
+    assert_eq!(
+        client.try_lock_rate(&user, &token2, &token1, &1000, &3600),
+        Err(Ok(SaveXError::InvalidRate))
+    );
+    client.set_reference_rate(&admin, &token1, &token2, &14_800_000);
+    assert_eq!(client.try_get_rate_lock(&7).err(), Some(Ok(SaveXError::RateLockNotFound)));
+
+    let lock_id = client.lock_rate(&user, &token1, &token2, &1000, &3600);
+
+    assert_eq!(
+        client.try_transfer_with_rate_lock(&other, &user, &100, &lock_id),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+    assert_eq!(
+        client.try_transfer_with_rate_lock(&user, &other, &1001, &lock_id),
+        Err(Ok(SaveXError::AmountExceedsLock))
+    );
+
+    assert_eq!(
+        client.try_release_expired_rate_lock(&lock_id),
+        Err(Ok(SaveXError::RateLockNotExpired))
+    );
+
+    env.ledger().set_timestamp(1000 + 3601);
+    assert_eq!(
+        client.try_transfer_with_rate_lock(&user, &other, &100, &lock_id),
+        Err(Ok(SaveXError::RateLockExpired))
+    );
+
+    client.cancel_rate_lock(&user, &lock_id);
+    assert_eq!(
+        client.try_cancel_rate_lock(&user, &lock_id),
+        Err(Ok(SaveXError::RateLockNotActive))
+    );
+    assert_eq!(
+        client.try_release_expired_rate_lock(&lock_id),
+        Err(Ok(SaveXError::RateLockNotActive))
+    );
+}
+
+#[test]
+fn test_package_errors() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+
+    client.initialize(&admin);
+    setup_package_prices(&env, &client, &admin, 0);
+
+    env.ledger().set_timestamp(1000);
+
+    assert_eq!(client.try_cancel_package(&user), Err(Ok(SaveXError::PackageNotFound)));
+    assert_eq!(
+        client.try_subscribe_package(&user, &PackageType::Family, &0),
+        Err(Ok(SaveXError::InvalidDuration))
+    );
+
+    client.subscribe_package(&user, &PackageType::Family, &30);
+    assert_eq!(
+        client.try_subscribe_package(&user, &PackageType::Premium, &30),
+        Err(Ok(SaveXError::PackageAlreadyActive))
+    );
+
+    client.cancel_package(&user);
+    assert_eq!(client.try_cancel_package(&user), Err(Ok(SaveXError::PackageNotActive)));
+}
+
+#[test]
+fn test_arbitrage_requires_router() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let bot_id = env.register(ArbitrageBot, ());
+    let bot = ArbitrageBotClient::new(&env, &bot_id);
+
+    let token1 = Address::generate(&env);
+    let token2 = Address::generate(&env);
+
+    assert_eq!(
+        bot.try_estimate_arbitrage_profit(&token1, &token2, &1000),
+        Err(Ok(SaveXError::RouterNotConfigured))
+    );
+
+    let executor = Address::generate(&env);
+    let mut path = soroban_sdk::Vec::new(&env);
+    path.push_back(token1.clone());
+    path.push_back(token2.clone());
+    assert_eq!(
+        bot.try_execute_triangular_arbitrage(&executor, &path, &1000, &0),
+        Err(Ok(SaveXError::InvalidPath))
+    );
+}
+
+#[test]
+fn test_transfer_events() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&user1, &1000);
+
+    env.ledger().set_timestamp(1000);
+
+    // Immediate transfer: created + executed
+    let immediate_id = client.transfer_immediate(&user1, &user2, &token.address, &100);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::TransferCreated {
+                id: immediate_id,
+                from: user1.clone(),
+                to: user2.clone(),
+                token: token.address.clone(),
+                amount: 100,
+                transfer_type: TransferType::Immediate,
+                execute_after: 0,
+            }),
+            event_entry(&env, &events::TransferExecuted {
+                id: immediate_id,
+                from: user1.clone(),
+                to: user2.clone(),
+                token: token.address.clone(),
+                amount: 100,
+            }),
+        ]
+    );
+
+    // Scheduled transfer: created now, executed later
+    let scheduled_id = client.transfer_scheduled(&user1, &user2, &token.address, &200, &2000);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::TransferCreated {
+                id: scheduled_id,
+                from: user1.clone(),
+                to: user2.clone(),
+                token: token.address.clone(),
+                amount: 200,
+                transfer_type: TransferType::Scheduled,
+                execute_after: 2000,
+            }),
+        ]
+    );
+
+    env.ledger().set_timestamp(2000);
+    client.execute_scheduled_transfer(&scheduled_id);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::TransferExecuted {
+                id: scheduled_id,
+                from: user1.clone(),
+                to: user2.clone(),
+                token: token.address.clone(),
+                amount: 200,
+            }),
+        ]
+    );
+
+    // Cancelled transfer
+    let cancelled_id = client.transfer_scheduled(&user1, &user2, &token.address, &300, &5000);
+    client.cancel_scheduled_transfer(&user1, &cancelled_id);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::TransferCancelled {
+                id: cancelled_id,
+                from: user1.clone(),
+                to: user2.clone(),
+                token: token.address.clone(),
+                amount: 300,
+            }),
+        ]
+    );
+}
+
+#[test]
+fn test_rate_lock_and_package_events() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+
+    client.initialize(&admin);
+    setup_package_prices(&env, &client, &admin, 0);
+
+    let (token1, token1_admin, token2, _router) = setup_swap_pair(&env, &client, &admin, 15000000);
+    let (token1, token2) = (token1.address, token2.address);
+    token1_admin.mint(&user, &20000);
+
+    env.ledger().set_timestamp(1000);
+
+    let lock_id = client.lock_rate(&user, &token1, &token2, &10000, &3600);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::HedgeReserveChanged {
+                token: token1.clone(),
+                delta: 12,
+                balance: 12,
+            }),
+            event_entry(&env, &events::RateLockCreated {
+                id: lock_id,
+                owner: user.clone(),
+                from_token: token1.clone(),
+                to_token: token2.clone(),
+                locked_rate: 15000000,
+                amount: 10000,
+                premium: 12,
+                expiry: 4600,
+            }),
+        ]
+    );
+
+    client.cancel_rate_lock(&user, &lock_id);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::RateLockCancelled {
+                id: lock_id,
+                owner: user.clone(),
+                refund: 10000,
+            }),
+        ]
+    );
+
+    client.subscribe_package(&user, &PackageType::Business, &30);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::PackageSubscribed {
+                owner: user.clone(),
+                package_type: PackageType::Business,
+                discount_rate: 2000,
+                start_date: 1000,
+                end_date: 1000 + 30 * 86400,
+                amount_paid: 0,
+            }),
+        ]
+    );
+
+    client.cancel_package(&user);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::PackageCancelled {
+                owner: user.clone(),
+                package_type: PackageType::Business,
+                refund: 0,
+            }),
+        ]
+    );
+
+    // An expired lock can be closed by anyone
+    let lock_id = client.lock_rate(&user, &token1, &token2, &5000, &3600);
+    env.ledger().set_timestamp(1000 + 3601);
+    client.release_expired_rate_lock(&lock_id);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::RateLockExpired {
+                id: lock_id,
+                owner: user.clone(),
+                refund: 5000,
+                keeper_reward: 0,
+            }),
+        ]
+    );
+}
+
+#[test]
+fn test_admin_events() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let router = Address::generate(&env);
+
+    client.initialize(&admin);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![&env, event_entry(&env, &events::Initialized { admin: admin.clone() })]
+    );
+
+    client.pause(&admin);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![&env, event_entry(&env, &events::Paused { admin: admin.clone() })]
+    );
+
+    client.unpause(&admin);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![&env, event_entry(&env, &events::Unpaused { admin: admin.clone() })]
+    );
+
+    client.set_router_address(&admin, &router);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![&env, event_entry(&env, &events::RouterUpdated { admin: admin.clone(), router })]
+    );
+}
+
+// Helper function to configure a 1% fee with no fixed part and a treasury
+fn setup_fees(env: &Env, client: &SaveXContractClient, admin: &Address) -> Address {
+    let treasury = Address::generate(env);
+    client.set_fee_structure(admin, &FeeStructure {
+        base_fee: 0,
+        percentage_fee: 100,    // 1%
+        min_fee: 0,
+        max_fee: 1_000_000,
+    });
+    client.set_treasury(admin, &treasury);
+    treasury
+}
+
+#[test]
+fn test_immediate_transfer_collects_fee() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+
+    client.initialize(&admin);
+    let treasury = setup_fees(&env, &client, &admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&user1, &10_000);
+
+    env.ledger().set_timestamp(1000);
+
+    let transfer_id = client.transfer_immediate(&user1, &user2, &token.address, &10_000);
+
+    let fee_event = event_entry(&env, &events::FeeCharged {
+        transfer_id,
+        payer: user1.clone(),
+        token: token.address.clone(),
+        fee: 100,
+    });
+    assert!(contract_events(&env, &contract_id).contains(fee_event));
+
+    // 1% fee goes to the treasury, the rest to the recipient
+    assert_eq!(token.balance(&user1), 0);
+    assert_eq!(token.balance(&user2), 9_900);
+    assert_eq!(token.balance(&treasury), 100);
+    assert_eq!(client.get_transfer(&transfer_id).amount, 9_900);
+
+    assert_eq!(
+        client.get_fee_receipt(&transfer_id),
+        FeeReceipt {
+            transfer_id,
+            payer: user1.clone(),
+            token: token.address.clone(),
+            gross_amount: 10_000,
+            fee: 100,
+            net_amount: 9_900,
+            charged_at: 1000,
+        }
+    );
+}
+
+#[test]
+fn test_scheduled_transfer_escrows_net_amount() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+
+    client.initialize(&admin);
+    let treasury = setup_fees(&env, &client, &admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&user1, &10_000);
+
+    env.ledger().set_timestamp(1000);
+
+    let transfer_id = client.transfer_scheduled(&user1, &user2, &token.address, &10_000, &2000);
+    assert_eq!(token.balance(&treasury), 100);
+    assert_eq!(token.balance(&contract_id), 9_900);
+
+    // Cancelling refunds the escrowed amount; the fee is not refunded
+    client.cancel_scheduled_transfer(&user1, &transfer_id);
+    assert_eq!(token.balance(&user1), 9_900);
+    assert_eq!(token.balance(&contract_id), 0);
+    assert_eq!(client.get_fee_receipt(&transfer_id).fee, 100);
+}
+
+#[test]
+fn test_batch_and_split_fees() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+
+    client.initialize(&admin);
+    let treasury = setup_fees(&env, &client, &admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&sender, &30_000);
+
+    env.ledger().set_timestamp(1000);
+
+    // Batch of 2: 10% batch discount on each line's 1% fee
+    let recipients = vec![&env, recipient1.clone(), recipient2.clone()];
+    let amounts = vec![&env, 10_000i128, 10_000i128];
+    let transfer_ids = client.transfer_batch(&sender, &recipients, &token.address, &amounts);
+
+    assert_eq!(token.balance(&recipient1), 9_910);
+    assert_eq!(token.balance(&recipient2), 9_910);
+    assert_eq!(token.balance(&treasury), 180);
+    assert_eq!(client.get_fee_receipt(&transfer_ids.get(0).unwrap()).fee, 90);
+
+    // Split: fee charged once on the total and shared between legs
+    let (immediate_id, scheduled_id) = client.transfer_split(
+        &sender,
+        &recipient1,
+        &token.address,
+        &10_000,
+        &50,
+        &2000,
+    );
+
+    assert_eq!(token.balance(&treasury), 280);
+    assert_eq!(client.get_transfer(&immediate_id).amount, 4_950);
+    assert_eq!(client.get_transfer(&scheduled_id).amount, 4_950);
+    assert_eq!(client.get_fee_receipt(&immediate_id).fee, 50);
+    assert_eq!(client.get_fee_receipt(&scheduled_id).fee, 50);
+    assert_eq!(token.balance(&contract_id), 4_950);
+}
+
+#[test]
+fn test_fee_configuration_errors() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let stranger = Address::generate(&env);
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    assert_eq!(client.try_get_treasury(), Err(Ok(SaveXError::TreasuryNotConfigured)));
+    assert_eq!(
+        client.try_set_treasury(&stranger, &stranger),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+
+    let invalid = FeeStructure {
+        base_fee: 0,
+        percentage_fee: 50,
+        min_fee: 1000,
+        max_fee: 10,     // max below min
+    };
+    assert_eq!(
+        client.try_set_fee_structure(&admin, &invalid),
+        Err(Ok(SaveXError::InvalidFeeStructure))
+    );
+
+    // Default structure has a 0.05 XLM minimum: too much for a tiny transfer
+    let treasury = Address::generate(&env);
+    client.set_treasury(&admin, &treasury);
+    assert_eq!(client.get_treasury(), treasury);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&user1, &1000);
+    assert_eq!(
+        client.try_transfer_immediate(&user1, &user2, &token.address, &100),
+        Err(Ok(SaveXError::FeeExceedsAmount))
+    );
+    assert_eq!(client.try_get_fee_receipt(&1).err(), Some(Ok(SaveXError::FeeReceiptNotFound)));
+}
+
+#[test]
+fn test_token_registry() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+    token_admin.mint(&user1, &1_000_000);
+
+    // Unregistered tokens are rejected
+    assert_eq!(
+        client.try_transfer_immediate(&user1, &user2, &token.address, &1000),
+        Err(Ok(SaveXError::TokenNotSupported))
+    );
+    assert_eq!(
+        client.try_get_token_info(&token.address).err(),
+        Some(Ok(SaveXError::TokenNotRegistered))
+    );
+    assert_eq!(
+        client.try_register_token(&admin, &token.address, &String::from_str(&env, "USDC"), &19),
+        Err(Ok(SaveXError::InvalidDecimals))
+    );
+
+    client.register_token(&admin, &token.address, &String::from_str(&env, "USDC"), &6);
+    assert_eq!(
+        client.get_token_info(&token.address),
+        TokenInfo {
+            address: token.address.clone(),
+            symbol: String::from_str(&env, "USDC"),
+            decimals: 6,
+            is_supported: true,
+        }
+    );
+
+    // Global stroop-denominated schedule is scaled down to 6 decimals
+    assert_eq!(
+        client.get_token_fee_structure(&token.address),
+        FeeStructure {
+            base_fee: 100000,
+            percentage_fee: 50,
+            min_fee: 50000,
+            max_fee: 10000000,
+        }
+    );
+
+    // Disabled tokens are rejected again
+    client.set_token_supported(&admin, &token.address, &false);
+    assert_eq!(
+        client.try_transfer_immediate(&user1, &user2, &token.address, &1000),
+        Err(Ok(SaveXError::TokenNotSupported))
+    );
+    client.set_token_supported(&admin, &token.address, &true);
+    client.transfer_immediate(&user1, &user2, &token.address, &1000);
+    assert_eq!(token.balance(&user2), 1000);
+}
+
+#[test]
+fn test_token_fee_structure() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let user1 = Address::generate(&env);
+    let user2 = Address::generate(&env);
+
+    client.initialize(&admin);
+    client.set_treasury(&admin, &treasury);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+    token_admin.mint(&user1, &1_000_000);
+    client.register_token(&admin, &token.address, &String::from_str(&env, "USDC"), &6);
+
+    // Scaled default: 0.5% of 100000 plus 0.1 base = 100500
+    assert_eq!(client.calculate_fee(&user1, &token.address, &100000, &false, &1).total, 100500);
+
+    let custom = FeeStructure {
+        base_fee: 10,
+        percentage_fee: 20,     // 0.2%
+        min_fee: 50,
+        max_fee: 5000,
+    };
+    client.set_token_fee_structure(&admin, &token.address, &custom);
+    assert_eq!(client.get_token_fee_structure(&token.address), custom);
+
+    // 0.2% of 100000 + 10 base = 210
+    client.transfer_immediate(&user1, &user2, &token.address, &100000);
+    assert_eq!(token.balance(&treasury), 210);
+    assert_eq!(token.balance(&user2), 99790);
+
+    let unknown = Address::generate(&env);
+    assert_eq!(
+        client.try_set_token_fee_structure(&admin, &unknown, &custom),
+        Err(Ok(SaveXError::TokenNotRegistered))
+    );
+    assert_eq!(
+        client.try_calculate_fee(&user1, &unknown, &100000, &false, &1).err(),
+        Some(Ok(SaveXError::TokenNotSupported))
+    );
+}
+
+#[test]
+fn test_paid_package_refund() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let user = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    // Paid packages need a price and a treasury
+    assert_eq!(
+        client.try_subscribe_package(&user, &PackageType::Family, &30),
+        Err(Ok(SaveXError::PackagePriceNotSet))
+    );
+    let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
+    assert_eq!(
+        client.try_set_package_price(&admin, &PackageType::Family, &token.address, &100),
+        Err(Ok(SaveXError::TreasuryNotConfigured))
+    );
+
+    client.set_treasury(&admin, &treasury);
+    client.set_package_price(&admin, &PackageType::Family, &token.address, &100);
+    assert_eq!(
+        client.get_package_price(&PackageType::Family),
+        PackagePrice { token: token.address.clone(), price_per_day: 100 }
+    );
+
+    token_admin.mint(&user, &10_000);
+    env.ledger().set_timestamp(1000);
+
+    // 30 days at 100 per day, held by the contract
+    client.subscribe_package(&user, &PackageType::Family, &30);
+    assert_eq!(token.balance(&user), 7_000);
+    assert_eq!(token.balance(&contract_id), 3_000);
+    assert_eq!(client.get_package(&user).unwrap().prepaid, 3_000);
+
+    // 10 days and an hour later: 19 whole days are unused
+    env.ledger().set_timestamp(1000 + 10 * 86400 + 3600);
+    client.cancel_package(&user);
+
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::PackageCancelled {
+                owner: user.clone(),
+                package_type: PackageType::Family,
+                refund: 1_900,
+            }),
+        ]
+    );
+    assert_eq!(token.balance(&user), 8_900);
+    assert_eq!(token.balance(&treasury), 1_100);
+    assert_eq!(token.balance(&contract_id), 0);
+    assert_eq!(client.get_package(&user).unwrap().prepaid, 0);
+
+    // A lapsed package is settled to the treasury on the next subscription
+    client.subscribe_package(&user, &PackageType::Family, &1);
+    env.ledger().set_timestamp(1000 + 12 * 86400);
+    client.subscribe_package(&user, &PackageType::Family, &1);
+    assert_eq!(token.balance(&treasury), 1_200);
+    assert_eq!(token.balance(&contract_id), 100);
+}
+
+#[test]
+fn test_package_usage_and_discount() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+    let recipient1 = Address::generate(&env);
+    let recipient2 = Address::generate(&env);
+
+    client.initialize(&admin);
+    let treasury = setup_fees(&env, &client, &admin);
+    setup_package_prices(&env, &client, &admin, 0);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&user, &100_000);
+
+    env.ledger().set_timestamp(1000);
+    client.subscribe_package(&user, &PackageType::Premium, &30);
+
+    // 25% off the 1% fee
+    client.transfer_immediate(&user, &recipient1, &token.address, &10_000);
+    assert_eq!(token.balance(&treasury), 75);
+    assert_eq!(token.balance(&recipient1), 9_925);
+
+    let recipients = vec![&env, recipient1.clone(), recipient2.clone()];
+    let amounts = vec![&env, 10_000i128, 20_000i128];
+    client.transfer_batch(&user, &recipients, &token.address, &amounts);
+
+    let package = client.get_package(&user).unwrap();
+    assert_eq!(package.transfer_count, 3);
+    assert_eq!(package.total_volume, 40_000);
+
+    // Usage is no longer tracked once the package has expired
+    env.ledger().set_timestamp(1000 + 31 * 86400);
+    client.transfer_immediate(&user, &recipient1, &token.address, &10_000);
+    assert_eq!(client.get_package(&user).unwrap().transfer_count, 3);
+}
+
+#[test]
+fn test_package_renew_upgrade_downgrade() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let treasury = Address::generate(&env);
+    let user = Address::generate(&env);
+
+    client.initialize(&admin);
+    client.set_treasury(&admin, &treasury);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
+    client.set_package_price(&admin, &PackageType::Family, &token.address, &100);
+    client.set_package_price(&admin, &PackageType::Business, &token.address, &200);
+    client.set_package_price(&admin, &PackageType::Premium, &token.address, &300);
+    token_admin.mint(&user, &100_000);
+
+    env.ledger().set_timestamp(1000);
+    client.subscribe_package(&user, &PackageType::Family, &30);
+
+    // 20 unused days: 2000 credit against 6000 for Premium
+    env.ledger().set_timestamp(1000 + 10 * 86400);
+    client.upgrade_package(&user, &PackageType::Premium);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::PackageChanged {
+                owner: user.clone(),
+                from_type: PackageType::Family,
+                to_type: PackageType::Premium,
+                amount_paid: 4_000,
+                refund: 0,
+            }),
+        ]
+    );
+    assert_eq!(token.balance(&treasury), 1_000);
+    assert_eq!(token.balance(&contract_id), 6_000);
+
+    let package = client.get_package(&user).unwrap();
+    assert_eq!(package.package_type, PackageType::Premium);
+    assert_eq!(package.discount_rate, 2500);
+    assert_eq!(package.end_date, 1000 + 30 * 86400);
+
+    assert_eq!(
+        client.try_upgrade_package(&user, &PackageType::Family),
+        Err(Ok(SaveXError::InvalidPackageChange))
+    );
+    assert_eq!(
+        client.try_downgrade_package(&user, &PackageType::Premium),
+        Err(Ok(SaveXError::InvalidPackageChange))
+    );
+
+    // Business for the same 20 days costs 4000: 2000 of the credit comes back
+    client.downgrade_package(&user, &PackageType::Business);
+    assert_eq!(token.balance(&contract_id), 4_000);
+    assert_eq!(token.balance(&user), 100_000 - 3_000 - 4_000 + 2_000);
+
+    // Renewal extends the end date at the package price
+    client.renew_package(&user, &10);
+    let package = client.get_package(&user).unwrap();
+    assert_eq!(package.end_date, 1000 + 40 * 86400);
+    assert_eq!(package.prepaid, 6_000);
+    assert_eq!(
+        client.try_renew_package(&user, &340),
+        Err(Ok(SaveXError::InvalidDuration))
+    );
+
+    client.cancel_package(&user);
+    assert_eq!(token.balance(&user), 99_000);
+    assert_eq!(
+        client.try_renew_package(&user, &10),
+        Err(Ok(SaveXError::PackageNotActive))
+    );
+
+    let history = client.get_package_history(&user);
+    assert_eq!(history.len(), 5);
+    assert_eq!(
+        history.get(0).unwrap(),
+        PackageHistoryEntry {
+            action: PackageAction::Subscribed,
+            package_type: PackageType::Family,
+            timestamp: 1000,
+            end_date: 1000 + 30 * 86400,
+            amount_paid: 3_000,
+            refund: 0,
+        }
+    );
+    assert_eq!(history.get(1).unwrap().action, PackageAction::Upgraded);
+    assert_eq!(history.get(2).unwrap().action, PackageAction::Downgraded);
+    assert_eq!(history.get(2).unwrap().refund, 2_000);
+    assert_eq!(history.get(3).unwrap().action, PackageAction::Renewed);
+    assert_eq!(history.get(4).unwrap().action, PackageAction::Cancelled);
+    assert_eq!(history.get(4).unwrap().refund, 6_000);
+}
+
+#[test]
+fn test_package_tier_quotas_and_steps() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    client.initialize(&admin);
+    let treasury = setup_fees(&env, &client, &admin);
+    setup_package_prices(&env, &client, &admin, 0);
+
+    let invalid = PackageTier {
+        discount_rate: 9000,
+        monthly_transfer_quota: 0,
+        monthly_volume_quota: 0,
+        discount_steps: vec![&env, DiscountStep { min_transfers: 1, extra_discount: 2000 }],
+        seat_limit: 0,
+    };
+    assert_eq!(
+        client.try_set_package_tier(&admin, &PackageType::Family, &invalid),
+        Err(Ok(SaveXError::InvalidPackageTier))
+    );
+
+    // 10% off, 20% from the third transfer, 3 transfers or 25000 per month
+    let tier = PackageTier {
+        discount_rate: 1000,
+        monthly_transfer_quota: 3,
+        monthly_volume_quota: 25_000,
+        discount_steps: vec![&env, DiscountStep { min_transfers: 2, extra_discount: 1000 }],
+        seat_limit: 0,
+    };
+    client.set_package_tier(&admin, &PackageType::Family, &tier);
+    assert_eq!(client.get_package_tier(&PackageType::Family), tier);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&user, &100_000);
+
+    env.ledger().set_timestamp(1000);
+    client.subscribe_package(&user, &PackageType::Family, &90);
+    assert_eq!(client.get_package(&user).unwrap().discount_rate, 1000);
+
+    client.transfer_immediate(&user, &recipient, &token.address, &10_000);
+    assert_eq!(token.balance(&treasury), 90);
+    client.transfer_immediate(&user, &recipient, &token.address, &5_000);
+    assert_eq!(token.balance(&treasury), 135);
+
+    // Third transfer reaches the discount step
+    client.transfer_immediate(&user, &recipient, &token.address, &10_000);
+    assert_eq!(token.balance(&treasury), 215);
+
+    // Fourth transfer is over quota: standard fee and an event
+    client.transfer_immediate(&user, &recipient, &token.address, &10_000);
+    assert!(contract_events(&env, &contract_id).contains(event_entry(
+        &env,
+        &events::PackageQuotaExceeded {
+            owner: user.clone(),
+            package_type: PackageType::Family,
+            period_transfer_count: 4,
+            period_volume: 35_000,
+        }
+    )));
+    assert_eq!(token.balance(&treasury), 315);
+
+    // Quotas reset with the next monthly period
+    env.ledger().set_timestamp(1000 + 30 * 86400);
+    client.transfer_immediate(&user, &recipient, &token.address, &10_000);
+    assert_eq!(token.balance(&treasury), 405);
+
+    let package = client.get_package(&user).unwrap();
+    assert_eq!(package.transfer_count, 5);
+    assert_eq!(package.total_volume, 45_000);
+    assert_eq!(package.period_start, 1000 + 30 * 86400);
+    assert_eq!(package.period_transfer_count, 1);
+}
+
+#[test]
+fn test_package_members_share_discount_and_quota() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let owner = Address::generate(&env);
+    let member1 = Address::generate(&env);
+    let member2 = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    client.initialize(&admin);
+    let treasury = setup_fees(&env, &client, &admin);
+    setup_package_prices(&env, &client, &admin, 0);
+
+    client.set_package_tier(&admin, &PackageType::Family, &PackageTier {
+        discount_rate: 1000,
+        monthly_transfer_quota: 2,
+        monthly_volume_quota: 0,
+        discount_steps: vec![&env],
+        seat_limit: 1,
+    });
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&owner, &100_000);
+    token_admin.mint(&member1, &100_000);
+
+    env.ledger().set_timestamp(1000);
+    assert_eq!(
+        client.try_add_package_member(&owner, &member1),
+        Err(Ok(SaveXError::PackageNotFound))
+    );
+    client.subscribe_package(&owner, &PackageType::Family, &30);
+
+    client.add_package_member(&owner, &member1);
+    assert_eq!(
+        client.try_add_package_member(&owner, &member1),
+        Err(Ok(SaveXError::MemberAlreadyAdded))
+    );
+    assert_eq!(
+        client.try_add_package_member(&owner, &member2),
+        Err(Ok(SaveXError::SeatLimitReached))
+    );
+    assert_eq!(client.get_package_members(&owner), vec![&env, member1.clone()]);
+
+    // Member gets the owner's discount and uses the shared quota
+    assert_eq!(client.calculate_fee(&member1, &token.address, &10_000, &false, &1).total, 90);
+    client.transfer_immediate(&member1, &recipient, &token.address, &10_000);
+    client.transfer_immediate(&owner, &recipient, &token.address, &10_000);
+    assert_eq!(token.balance(&treasury), 180);
+
+    client.transfer_immediate(&member1, &recipient, &token.address, &10_000);
+    assert_eq!(token.balance(&treasury), 280);
+    assert_eq!(client.get_package(&owner).unwrap().transfer_count, 3);
+
+    client.remove_package_member(&owner, &member1);
+    assert_eq!(client.get_package_members(&owner).len(), 0);
+    assert_eq!(client.calculate_fee(&member1, &token.address, &10_000, &false, &1).total, 100);
+    assert_eq!(
+        client.try_remove_package_member(&owner, &member1),
+        Err(Ok(SaveXError::MemberNotFound))
+    );
+}
+
+#[test]
+fn test_transfer_with_rate_lock_uses_hedge_reserve() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token_a, token_a_admin) = create_token_contract(&env, &admin);
+    let (token_b, token_b_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token_a.address);
+    register_token(&env, &client, &admin, &token_b.address);
+
+    let router = setup_mock_router(&env, &client, &admin);
+    token_b_admin.mint(&router.address, &1_000_000);
+
+    token_a_admin.mint(&user, &20_000);
+    token_b_admin.mint(&admin, &1_000);
+
+    env.ledger().set_timestamp(1000);
+
+    // Locked at 1.5 B per A
+    router.set_rate(&15_000_000);
+    client.set_reference_rate(&admin, &token_a.address, &token_b.address, &15_000_000);
+    let lock_id = client.lock_rate(&user, &token_a.address, &token_b.address, &10_000, &3600);
+
+    // Pool pays 1.4, too far off the reference rate for the reserve to cover
+    router.set_rate(&14_000_000);
+    client.fund_hedge_reserve(&admin, &token_b.address, &500);
+    assert_eq!(
+        client.try_transfer_with_rate_lock(&user, &recipient, &1000, &lock_id),
+        Err(Ok(SaveXError::SlippageExceeded))
+    );
+    client.withdraw_hedge_reserve(&admin, &token_b.address, &500);
+
+    // Pool pays 1.48: the 20 shortfall cannot be covered yet
+    router.set_rate(&14_800_000);
+    assert_eq!(
+        client.try_transfer_with_rate_lock(&user, &recipient, &1000, &lock_id),
+        Err(Ok(SaveXError::InsufficientHedgeReserve))
+    );
+    assert_eq!(client.get_rate_lock(&lock_id).remaining_amount, 10_000);
+
+    // A reserve smaller than the shortfall still isn't enough
+    client.fund_hedge_reserve(&admin, &token_b.address, &10);
+    assert_eq!(
+        client.try_transfer_with_rate_lock(&user, &recipient, &1000, &lock_id),
+        Err(Ok(SaveXError::InsufficientHedgeReserve))
+    );
+    assert_eq!(token_b.balance(&contract_id), 10);
+    assert_eq!(client.get_hedge_reserve(&token_b.address), 10);
+
+    client.fund_hedge_reserve(&admin, &token_b.address, &490);
+    let transfer_id = client.transfer_with_rate_lock(&user, &recipient, &1000, &lock_id);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::HedgeReserveChanged {
+                token: token_b.address.clone(),
+                delta: -20,
+                balance: 480,
+            }),
+            event_entry(&env, &events::TransferCreated {
+                id: transfer_id,
+                from: user.clone(),
+                to: recipient.clone(),
+                token: token_b.address.clone(),
+                amount: 1500,
+                transfer_type: TransferType::Immediate,
+                execute_after: 0,
+            }),
+            event_entry(&env, &events::TransferExecuted {
+                id: transfer_id,
+                from: user.clone(),
+                to: recipient.clone(),
+                token: token_b.address.clone(),
+                amount: 1500,
+            }),
+            event_entry(&env, &events::RateLockUsed {
+                id: lock_id,
+                owner: user.clone(),
+                transfer_id,
+                amount: 1000,
+                amount_out: 1500,
+                remaining_amount: 9_000,
+            }),
+        ]
+    );
+    assert_eq!(token_b.balance(&recipient), 1500);
+    assert_eq!(client.get_hedge_reserve(&token_b.address), 480);
+
+    // Pool pays 1.52: the 20 surplus goes to the reserve
+    router.set_rate(&15_200_000);
+    client.transfer_with_rate_lock(&user, &recipient, &1000, &lock_id);
+    assert_eq!(token_b.balance(&recipient), 3000);
+    assert_eq!(client.get_hedge_reserve(&token_b.address), 500);
+    assert_eq!(token_b.balance(&contract_id), 500);
+
+    assert_eq!(
+        client.try_withdraw_hedge_reserve(&admin, &token_b.address, &501),
+        Err(Ok(SaveXError::InsufficientHedgeReserve))
+    );
+    client.withdraw_hedge_reserve(&admin, &token_b.address, &500);
+    assert_eq!(token_b.balance(&admin), 1000);
+    assert_eq!(token_b.balance(&contract_id), 0);
+}
+
+#[test]
+fn test_rate_lock_partial_fills() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token_a, token_a_admin) = create_token_contract(&env, &admin);
+    let (token_b, token_b_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token_a.address);
+    register_token(&env, &client, &admin, &token_b.address);
+
+    // Pool pays exactly the locked rate
+    let router = setup_mock_router(&env, &client, &admin);
+    router.set_rate(&15_000_000);
+    token_b_admin.mint(&router.address, &1_000_000);
+    token_a_admin.mint(&user, &10_000);
+
+    env.ledger().set_timestamp(1000);
+
+    client.set_reference_rate(&admin, &token_a.address, &token_b.address, &15_000_000);
+    let lock_id = client.lock_rate(&user, &token_a.address, &token_b.address, &3000, &3600);
+    let rate_lock = client.get_rate_lock(&lock_id);
+    assert_eq!(rate_lock.remaining_amount, 3000);
+    assert_eq!(rate_lock.fills.len(), 0);
+
+    let first_id = client.transfer_with_rate_lock(&user, &recipient, &1000, &lock_id);
+    let rate_lock = client.get_rate_lock(&lock_id);
+    assert_eq!(rate_lock.remaining_amount, 2000);
+    assert!(rate_lock.is_active);
+
+    assert_eq!(
+        client.try_transfer_with_rate_lock(&user, &recipient, &2500, &lock_id),
+        Err(Ok(SaveXError::AmountExceedsLock))
+    );
+
+    // Using the rest exhausts the lock
+    let second_id = client.transfer_with_rate_lock(&user, &recipient, &2000, &lock_id);
+    let rate_lock = client.get_rate_lock(&lock_id);
+    assert_eq!(rate_lock.remaining_amount, 0);
+    assert!(!rate_lock.is_active);
+    assert_eq!(rate_lock.fills, vec![&env, first_id, second_id]);
+    assert_eq!(rate_lock.amount, 3000);
+    assert_eq!(token_b.balance(&recipient), 4500);
+
+    assert_eq!(
+        client.try_transfer_with_rate_lock(&user, &recipient, &1, &lock_id),
+        Err(Ok(SaveXError::RateLockNotActive))
+    );
+}
+
+#[test]
+fn test_sweep_expired_locks() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+    let other = Address::generate(&env);
+    let keeper = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token_a, token_a_admin) = create_token_contract(&env, &admin);
+    let (token_b, _) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token_a.address);
+    register_token(&env, &client, &admin, &token_b.address);
+    setup_mock_router(&env, &client, &admin);
+
+    token_a_admin.mint(&user, &100_000);
+    token_a_admin.mint(&other, &100_000);
+
+    env.ledger().set_timestamp(1000);
+
+    // Premium for one hour is 0.12%
+    client.set_reference_rate(&admin, &token_a.address, &token_b.address, &10_000_000);
+    let short_id = client.lock_rate(&user, &token_a.address, &token_b.address, &10_000, &3600);
+    let long_id = client.lock_rate(&other, &token_a.address, &token_b.address, &20_000, &7200);
+    let cancelled_id = client.lock_rate(&user, &token_a.address, &token_b.address, &5000, &3600);
+    client.cancel_rate_lock(&user, &cancelled_id);
+    assert_eq!(token_a.balance(&user), 100_000 - 10_012 - 6);
+
+    // Only the expired, still active lock is swept
+    env.ledger().set_timestamp(1000 + 3601);
+    let swept = client.sweep_expired_locks(&keeper, &vec![&env, short_id, long_id, cancelled_id, 99]);
+    assert_eq!(swept, 1);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::RateLockExpired {
+                id: short_id,
+                owner: user.clone(),
+                refund: 9990,
+                keeper_reward: 10,
+            }),
+        ]
+    );
+
+    assert_eq!(token_a.balance(&keeper), 10);
+    assert_eq!(token_a.balance(&user), 100_000 - 12 - 6 - 10);
+    let rate_lock = client.get_rate_lock(&short_id);
+    assert!(!rate_lock.is_active);
+    assert_eq!(rate_lock.remaining_amount, 0);
+    assert!(client.get_rate_lock(&long_id).is_active);
+
+    // Sweeping again is a no-op, the second lock follows once expired
+    assert_eq!(client.sweep_expired_locks(&keeper, &vec![&env, short_id]), 0);
+    env.ledger().set_timestamp(1000 + 7201);
+    assert_eq!(client.sweep_expired_locks(&keeper, &vec![&env, short_id, long_id]), 1);
+    assert_eq!(token_a.balance(&keeper), 30);
+    assert_eq!(token_a.balance(&other), 100_000 - 28 - 20);
+
+    let mut too_many = Vec::new(&env);
+    for id in 0..51u64 {
+        too_many.push_back(id);
+    }
+    assert_eq!(
+        client.try_sweep_expired_locks(&keeper, &too_many),
+        Err(Ok(SaveXError::BatchTooLarge))
+    );
+}
+
+#[test]
+fn test_user_indexes() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let carol = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token_a, token_a_admin) = create_token_contract(&env, &admin);
+    let (token_b, _) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token_a.address);
+    register_token(&env, &client, &admin, &token_b.address);
+    setup_mock_router(&env, &client, &admin);
+    token_a_admin.mint(&alice, &100_000);
+    token_a_admin.mint(&bob, &100_000);
+
+    env.ledger().set_timestamp(1000);
+
+    let t1 = client.transfer_immediate(&alice, &bob, &token_a.address, &100);
+    let t2 = client.transfer_scheduled(&alice, &carol, &token_a.address, &200, &2000);
+    let t3 = client.transfer_scheduled(&bob, &alice, &token_a.address, &300, &2000);
+    let t4 = client.transfer_immediate(&alice, &carol, &token_a.address, &400);
+
+    let ids = |transfers: Vec<Transfer>| {
+        let mut ids = Vec::new(&env);
+        for transfer in transfers.iter() {
+            ids.push_back(transfer.id);
+        }
+        ids
+    };
+
+    // Pages follow creation order
+    assert_eq!(ids(client.get_transfers_by_sender(&alice, &0, &10)), vec![&env, t1, t2, t4]);
+    assert_eq!(ids(client.get_transfers_by_sender(&alice, &0, &2)), vec![&env, t1, t2]);
+    assert_eq!(ids(client.get_transfers_by_sender(&alice, &2, &2)), vec![&env, t4]);
+    assert_eq!(client.get_transfers_by_sender(&alice, &3, &2).len(), 0);
+    assert_eq!(ids(client.get_transfers_by_recipient(&carol, &0, &10)), vec![&env, t2, t4]);
+    assert_eq!(ids(client.get_transfers_by_recipient(&alice, &0, &10)), vec![&env, t3]);
+    assert_eq!(client.get_transfers_by_sender(&carol, &0, &10).len(), 0);
+
+    // Pending transfers cover both sides and drop out once settled
+    assert_eq!(ids(client.get_pending_transfers(&alice, &0, &50)), vec![&env, t2, t3]);
+    assert_eq!(ids(client.get_pending_transfers(&carol, &0, &50)), vec![&env, t2]);
+
+    client.cancel_scheduled_transfer(&bob, &t3);
+    assert_eq!(ids(client.get_pending_transfers(&alice, &0, &50)), vec![&env, t2]);
+    assert_eq!(client.get_pending_transfers(&bob, &0, &50).len(), 0);
+
+    env.ledger().set_timestamp(2001);
+    client.execute_scheduled_transfer(&t2);
+    assert_eq!(client.get_pending_transfers(&alice, &0, &50).len(), 0);
+    assert_eq!(client.get_pending_transfers(&carol, &0, &50).len(), 0);
+
+    // Flooding an address with small transfers only grows its index; settling
+    // one doesn't rewrite it and it is read a page at a time
+    let mut spam = Vec::new(&env);
+    for _ in 0..60 {
+        spam.push_back(client.transfer_scheduled(&bob, &carol, &token_a.address, &100, &3000));
+    }
+    assert_eq!(client.get_pending_transfers(&carol, &0, &50).len(), 49);
+    assert_eq!(client.get_pending_transfers(&carol, &50, &50).len(), 11);
+    client.cancel_scheduled_transfer(&bob, &spam.get(59).unwrap());
+    assert_eq!(client.get_pending_transfers(&carol, &50, &50).len(), 10);
+    assert_eq!(client.get_pending_transfers(&carol, &50, &100).len(), 10);
+
+    // Rate locks are indexed per owner
+    client.set_reference_rate(&admin, &token_a.address, &token_b.address, &10_000_000);
+    let l1 = client.lock_rate(&alice, &token_a.address, &token_b.address, &1000, &3600);
+    let l2 = client.lock_rate(&bob, &token_a.address, &token_b.address, &1000, &3600);
+    let l3 = client.lock_rate(&alice, &token_a.address, &token_b.address, &2000, &3600);
+
+    let locks = client.get_rate_locks_by_owner(&alice, &0, &10);
+    assert_eq!(locks.len(), 2);
+    assert_eq!(locks.get(0).unwrap().id, l1);
+    assert_eq!(locks.get(1).unwrap().id, l3);
+    assert_eq!(client.get_rate_locks_by_owner(&bob, &0, &10).get(0).unwrap().id, l2);
+    assert_eq!(client.get_rate_locks_by_owner(&alice, &1, &10).get(0).unwrap().id, l3);
+}
+
+#[test]
+fn test_claimable_transfer() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&sender, &10_000);
+
+    env.ledger().set_timestamp(1000);
+
+    let claim_code = Bytes::from_slice(&env, b"remit-4521");
+    let claim_hash: BytesN<32> = env.crypto().sha256(&claim_code).into();
+
+    assert_eq!(
+        client.try_transfer_claimable(&sender, &token.address, &1000, &claim_hash, &1000),
+        Err(Ok(SaveXError::InvalidTimestamp))
+    );
+
+    let transfer_id = client.transfer_claimable(&sender, &token.address, &1000, &claim_hash, &5000);
+    let transfer = client.get_transfer(&transfer_id);
+    assert_eq!(transfer.transfer_type, TransferType::Claimable);
+    assert_eq!(transfer.status, TransferStatus::Claimable);
+    assert_eq!(transfer.time_bound_kind, TimeBoundKind::Before);
+    assert_eq!(token.balance(&contract_id), 1000);
+    assert_eq!(client.get_pending_transfers(&sender, &0, &50).len(), 1);
+
+    assert_eq!(
+        client.try_claim_transfer(&transfer_id, &Bytes::from_slice(&env, b"guess"), &recipient),
+        Err(Ok(SaveXError::InvalidClaimCode))
+    );
+    assert_eq!(
+        client.try_reclaim_transfer(&sender, &transfer_id),
+        Err(Ok(SaveXError::ClaimNotExpired))
+    );
+    assert_eq!(
+        client.try_execute_scheduled_transfer(&transfer_id),
+        Err(Ok(SaveXError::TransferNotPending))
+    );
+
+    client.claim_transfer(&transfer_id, &claim_code, &recipient);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::TransferExecuted {
+                id: transfer_id,
+                from: sender.clone(),
+                to: recipient.clone(),
+                token: token.address.clone(),
+                amount: 1000,
+            }),
+        ]
+    );
+
+    assert_eq!(token.balance(&recipient), 1000);
+    let transfer = client.get_transfer(&transfer_id);
+    assert_eq!(transfer.status, TransferStatus::Claimed);
+    assert_eq!(transfer.to, recipient);
+    assert_eq!(client.get_pending_transfers(&sender, &0, &50).len(), 0);
+    assert_eq!(client.get_transfers_by_recipient(&recipient, &0, &10).len(), 1);
+    assert_eq!(
+        client.try_claim_transfer(&transfer_id, &claim_code, &recipient),
+        Err(Ok(SaveXError::TransferNotPending))
+    );
+
+    // Unclaimed funds go back to the sender after the deadline
+    let expired_id = client.transfer_claimable(&sender, &token.address, &2000, &claim_hash, &6000);
+    env.ledger().set_timestamp(6001);
+    assert_eq!(
+        client.try_claim_transfer(&expired_id, &claim_code, &recipient),
+        Err(Ok(SaveXError::ClaimExpired))
+    );
+    assert_eq!(
+        client.try_reclaim_transfer(&recipient, &expired_id),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+
+    client.reclaim_transfer(&sender, &expired_id);
+    assert_eq!(token.balance(&sender), 10_000 - 1000);
+    assert_eq!(client.get_transfer(&expired_id).status, TransferStatus::Cancelled);
+    assert_eq!(client.get_pending_transfers(&sender, &0, &50).len(), 0);
+}
+
+#[test]
+fn test_recurring_transfer() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
+    let treasury = setup_fees(&env, &client, &admin);
+    token_admin.mint(&sender, &100_000);
+
+    env.ledger().set_timestamp(1000);
+
+    assert_eq!(
+        client.try_create_recurring_transfer(&sender, &recipient, &token.address, &1000, &0, &3),
+        Err(Ok(SaveXError::InvalidSchedule))
+    );
+    assert_eq!(
+        client.try_create_recurring_transfer(&sender, &recipient, &token.address, &1000, &86400, &121),
+        Err(Ok(SaveXError::InvalidSchedule))
+    );
+
+    // Three installments of 1000 are escrowed up front
+    let id = client.create_recurring_transfer(&sender, &recipient, &token.address, &1000, &86400, &3);
+    assert_eq!(token.balance(&contract_id), 3000);
+    assert_eq!(client.get_recurring_transfer(&id).next_execution, 1000 + 86400);
+
+    assert_eq!(
+        client.try_execute_due_installment(&id),
+        Err(Ok(SaveXError::TimeBoundNotMet))
+    );
+
+    env.ledger().set_timestamp(1000 + 86400);
+    let first_id = client.execute_due_installment(&id);
+    assert!(contract_events(&env, &contract_id).contains(event_entry(
+        &env,
+        &events::RecurringInstallmentExecuted {
+            id,
+            transfer_id: first_id,
+            installment: 1,
+            next_execution: 1000 + 2 * 86400,
+        },
+    )));
+
+    // 1% fee comes out of the escrowed installment
+    assert_eq!(token.balance(&recipient), 990);
+    assert_eq!(token.balance(&treasury), 10);
+    let transfer = client.get_transfer(&first_id);
+    assert_eq!(transfer.transfer_type, TransferType::Recurring);
+    assert_eq!(transfer.amount, 990);
+    assert_eq!(client.get_fee_receipt(&first_id).fee, 10);
+
+    // Pausing shifts the schedule by the paused time
+    client.pause_recurring_transfer(&sender, &id);
+    env.ledger().set_timestamp(1000 + 3 * 86400);
+    assert_eq!(
+        client.try_execute_due_installment(&id),
+        Err(Ok(SaveXError::TransferNotPending))
+    );
+    assert_eq!(
+        client.try_pause_recurring_transfer(&recipient, &id),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+    client.resume_recurring_transfer(&sender, &id);
+    assert_eq!(client.get_recurring_transfer(&id).next_execution, 1000 + 4 * 86400);
+    assert_eq!(
+        client.try_resume_recurring_transfer(&sender, &id),
+        Err(Ok(SaveXError::TransferNotPending))
+    );
+
+    env.ledger().set_timestamp(1000 + 4 * 86400);
+    client.execute_due_installment(&id);
+
+    // Cancelling refunds the one unpaid installment
+    client.cancel_recurring_transfer(&sender, &id);
+    assert_eq!(token.balance(&sender), 100_000 - 2000);
+    assert_eq!(token.balance(&contract_id), 0);
+    let recurring = client.get_recurring_transfer(&id);
+    assert_eq!(recurring.status, RecurringStatus::Cancelled);
+    assert_eq!(recurring.executed_count, 2);
+    assert_eq!(recurring.transfers.len(), 2);
+
+    // A schedule completes after its last installment
+    let id = client.create_recurring_transfer(&sender, &recipient, &token.address, &500, &60, &2);
+    env.ledger().set_timestamp(1000 + 4 * 86400 + 120);
+    client.execute_due_installment(&id);
+    client.execute_due_installment(&id);
+    assert_eq!(client.get_recurring_transfer(&id).status, RecurringStatus::Completed);
+    assert_eq!(
+        client.try_cancel_recurring_transfer(&sender, &id),
+        Err(Ok(SaveXError::TransferNotPending))
+    );
+}
+
+#[test]
+fn test_escrow_release_and_disputes() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let buyer = Address::generate(&env);
+    let seller = Address::generate(&env);
+    let arbiter = Address::generate(&env);
+    let approver_1 = Address::generate(&env);
+    let approver_2 = Address::generate(&env);
+    let approver_3 = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&buyer, &100_000);
+
+    env.ledger().set_timestamp(1000);
+
+    let approvers = vec![&env, approver_1.clone(), approver_2.clone(), approver_3.clone()];
+    let no_approvers = Vec::new(&env);
+    let release = |approvers: &Vec<Address>, threshold: u32| EscrowRelease {
+        arbiter: arbiter.clone(),
+        approvers: approvers.clone(),
+        threshold,
+    };
+
+    assert_eq!(
+        client.try_transfer_escrow(&buyer, &seller, &token.address, &1000, &release(&approvers, 4), &5000),
+        Err(Ok(SaveXError::InvalidApprovers))
+    );
+    assert_eq!(
+        client.try_transfer_escrow(&buyer, &seller, &token.address, &1000, &release(&no_approvers, 1), &5000),
+        Err(Ok(SaveXError::InvalidApprovers))
+    );
+    assert_eq!(
+        client.try_transfer_escrow(
+            &buyer,
+            &seller,
+            &token.address,
+            &1000,
+            &release(&vec![&env, approver_1.clone(), approver_1.clone()], 1),
+            &5000,
+        ),
+        Err(Ok(SaveXError::InvalidApprovers))
+    );
+
+    // 2-of-3 approvers release the funds
+    let multisig_id = client.transfer_escrow(&buyer, &seller, &token.address, &1000, &release(&approvers, 2), &5000);
+    assert_eq!(client.get_transfer(&multisig_id).status, TransferStatus::Locked);
+    assert_eq!(
+        client.try_execute_scheduled_transfer(&multisig_id),
+        Err(Ok(SaveXError::InvalidTimebound))
+    );
+    assert_eq!(
+        client.try_cancel_scheduled_transfer(&buyer, &multisig_id),
+        Err(Ok(SaveXError::InvalidTimebound))
+    );
+    assert_eq!(
+        client.try_approve_escrow(&seller, &multisig_id),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+
+    assert!(!client.approve_escrow(&approver_1, &multisig_id));
+    assert!(!client.approve_escrow(&approver_1, &multisig_id));
+    assert_eq!(token.balance(&seller), 0);
+    assert!(client.approve_escrow(&approver_3, &multisig_id));
+    assert_eq!(token.balance(&seller), 1000);
+    assert_eq!(client.get_transfer(&multisig_id).status, TransferStatus::Completed);
+    assert_eq!(client.get_escrow_terms(&multisig_id).approvals.len(), 2);
+
+    // The arbiter alone can release
+    let arbiter_id = client.transfer_escrow(&buyer, &seller, &token.address, &2000, &release(&no_approvers, 0), &5000);
+    assert!(client.approve_escrow(&arbiter, &arbiter_id));
+    assert_eq!(token.balance(&seller), 3000);
+
+    // A dispute freezes the funds until the arbiter refunds them
+    let disputed_id = client.transfer_escrow(&buyer, &seller, &token.address, &3000, &release(&approvers, 1), &5000);
+    client.dispute_escrow(&seller, &disputed_id);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::EscrowDisputed { id: disputed_id, raised_by: seller.clone() }),
+        ]
+    );
+    assert_eq!(
+        client.try_approve_escrow(&approver_2, &disputed_id),
+        Err(Ok(SaveXError::TransferNotPending))
+    );
+    assert_eq!(
+        client.try_resolve_dispute(&approver_2, &disputed_id, &true),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+
+    env.ledger().set_timestamp(6000);
+    assert_eq!(
+        client.try_refund_escrow(&buyer, &disputed_id),
+        Err(Ok(SaveXError::TransferNotPending))
+    );
+    client.resolve_dispute(&arbiter, &disputed_id, &false);
+    assert_eq!(client.get_transfer(&disputed_id).status, TransferStatus::Cancelled);
+    assert_eq!(token.balance(&buyer), 100_000 - 3000);
+
+    // Unreleased escrows are refunded after the deadline
+    let expiring_id = client.transfer_escrow(&buyer, &seller, &token.address, &500, &release(&approvers, 2), &7000);
+    assert_eq!(
+        client.try_refund_escrow(&buyer, &expiring_id),
+        Err(Ok(SaveXError::TimeBoundNotMet))
+    );
+    env.ledger().set_timestamp(7001);
+    assert_eq!(
+        client.try_approve_escrow(&arbiter, &expiring_id),
+        Err(Ok(SaveXError::TimeBoundNotMet))
+    );
+    assert_eq!(
+        client.try_dispute_escrow(&seller, &expiring_id),
+        Err(Ok(SaveXError::TimeBoundNotMet))
+    );
+    client.refund_escrow(&buyer, &expiring_id);
+    assert_eq!(token.balance(&buyer), 100_000 - 3000);
+    assert_eq!(client.get_pending_transfers(&buyer, &0, &50).len(), 0);
+}
+
+#[test]
+fn test_multi_token_scheduled_batch() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let payer = Address::generate(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (usdc, usdc_admin) = create_token_contract(&env, &admin);
+    let (eurc, eurc_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &usdc.address);
+    register_token(&env, &client, &admin, &eurc.address);
+    usdc_admin.mint(&payer, &100_000);
+    eurc_admin.mint(&payer, &100_000);
+
+    env.ledger().set_timestamp(1000);
+
+    let entry = |recipient: &Address, token: &Address, amount: i128, execute_after: u64| BatchEntry {
+        recipient: recipient.clone(),
+        token: token.clone(),
+        amount,
+        execute_after,
+    };
+
+    assert_eq!(
+        client.try_create_batch(&payer, &Vec::new(&env)),
+        Err(Ok(SaveXError::EmptyBatch))
+    );
+    assert_eq!(
+        client.try_create_batch(&payer, &vec![&env, entry(&alice, &usdc.address, 100, 500)]),
+        Err(Ok(SaveXError::InvalidTimestamp))
+    );
+
+    let batch_id = client.create_batch(
+        &payer,
+        &vec![
+            &env,
+            entry(&alice, &usdc.address, 1000, 0),
+            entry(&bob, &eurc.address, 2000, 0),
+            entry(&alice, &eurc.address, 3000, 5000),
+            entry(&bob, &usdc.address, 4000, 5000),
+        ],
+    );
+    assert!(contract_events(&env, &contract_id).contains(event_entry(
+        &env,
+        &events::BatchCreated {
+            id: batch_id,
+            creator: payer.clone(),
+            transfer_count: 4,
+        },
+    )));
+
+    // Immediate lines are paid, scheduled lines are escrowed
+    assert_eq!(usdc.balance(&alice), 1000);
+    assert_eq!(eurc.balance(&bob), 2000);
+    assert_eq!(eurc.balance(&contract_id), 3000);
+    assert_eq!(usdc.balance(&contract_id), 4000);
+
+    let batch = client.get_batch(&batch_id);
+    assert_eq!(batch.creator, payer);
+    assert_eq!(batch.transfer_ids.len(), 4);
+
+    let transfers = client.get_batch_transfers(&batch_id);
+    assert_eq!(transfers.get(0).unwrap().status, TransferStatus::Completed);
+    assert_eq!(transfers.get(2).unwrap().status, TransferStatus::Locked);
+    assert_eq!(transfers.get(2).unwrap().token, eurc.address);
+
+    // A scheduled line can still run on its own
+    env.ledger().set_timestamp(5000);
+    client.execute_scheduled_transfer(&transfers.get(2).unwrap().id);
+    assert_eq!(eurc.balance(&alice), 3000);
+
+    assert_eq!(
+        client.try_cancel_batch(&alice, &batch_id),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+
+    // Cancelling refunds only the legs still locked
+    assert_eq!(client.cancel_batch(&payer, &batch_id), 1);
+    assert_eq!(usdc.balance(&payer), 100_000 - 1000);
+    assert_eq!(usdc.balance(&contract_id), 0);
+    assert_eq!(
+        client.get_batch_transfers(&batch_id).get(3).unwrap().status,
+        TransferStatus::Cancelled
+    );
+    assert_eq!(client.get_batch(&batch_id).status, BatchStatus::Cancelled);
+    assert_eq!(
+        client.try_cancel_batch(&payer, &batch_id),
+        Err(Ok(SaveXError::TransferNotPending))
+    );
+}
+
+#[test]
+fn test_batch_records() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let payer = Address::generate(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (usdc, usdc_admin) = create_token_contract(&env, &admin);
+    let (eurc, eurc_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &usdc.address);
+    register_token(&env, &client, &admin, &eurc.address);
+    setup_fees(&env, &client, &admin);
+    usdc_admin.mint(&payer, &100_000);
+    eurc_admin.mint(&payer, &100_000);
+
+    env.ledger().set_timestamp(1000);
+
+    // Legacy batches and splits are linked through a batch record
+    let ids = client.transfer_batch(
+        &payer,
+        &vec![&env, alice.clone(), bob.clone()],
+        &usdc.address,
+        &vec![&env, 1000, 2000],
+    );
+    let batch_id = client.get_transfer(&ids.get(0).unwrap()).batch_id;
+    assert!(batch_id != 0);
+    let batch = client.get_batch(&batch_id);
+    assert_eq!(batch.transfer_ids, ids);
+    assert_eq!(batch.totals.get(usdc.address.clone()), Some(3000));
+    assert_eq!(batch.status, BatchStatus::Settled);
+
+    let (immediate_id, scheduled_id) = client.transfer_split(&payer, &alice, &usdc.address, &4000, &50, &3000);
+    let split_batch_id = client.get_transfer(&immediate_id).batch_id;
+    assert_eq!(client.get_transfer(&scheduled_id).batch_id, split_batch_id);
+    let split_batch = client.get_batch(&split_batch_id);
+    assert_eq!(split_batch.pending_legs, 1);
+    assert_eq!(split_batch.totals.get(usdc.address.clone()), Some(4000));
+
+    // Payroll run with legs due at different times
+    let entry = |recipient: &Address, token: &Address, amount: i128, execute_after: u64| BatchEntry {
+        recipient: recipient.clone(),
+        token: token.clone(),
+        amount,
+        execute_after,
+    };
+    let payroll_id = client.create_batch(
+        &payer,
+        &vec![
+            &env,
+            entry(&alice, &usdc.address, 1000, 0),
+            entry(&alice, &eurc.address, 2000, 2000),
+            entry(&bob, &eurc.address, 3000, 2000),
+            entry(&bob, &usdc.address, 4000, 4000),
+        ],
+    );
+    let payroll = client.get_batch(&payroll_id);
+    assert_eq!(payroll.creator, payer);
+    assert_eq!(payroll.pending_legs, 3);
+    assert_eq!(payroll.totals.get(usdc.address.clone()), Some(5000));
+    assert_eq!(payroll.totals.get(eurc.address.clone()), Some(5000));
+    assert_eq!(payroll.status, BatchStatus::Pending);
+
+    assert_eq!(client.execute_batch(&payroll_id), 0);
+
+    env.ledger().set_timestamp(2000);
+    assert_eq!(client.execute_batch(&payroll_id), 2);
+    assert!(contract_events(&env, &contract_id).contains(event_entry(
+        &env,
+        &events::BatchExecuted { id: payroll_id, executed: 2, pending_legs: 1 },
+    )));
+    assert_eq!(eurc.balance(&alice), 1986); // Batch-discounted fee
+    assert_eq!(eurc.balance(&bob), 2979);
+    assert_eq!(client.get_batch(&payroll_id).pending_legs, 1);
+
+    // The last leg runs on its own and settles the batch
+    env.ledger().set_timestamp(4000);
+    client.execute_scheduled_transfer(&payroll.transfer_ids.get(3).unwrap());
+    let payroll = client.get_batch(&payroll_id);
+    assert_eq!(payroll.pending_legs, 0);
+    assert_eq!(payroll.status, BatchStatus::Settled);
+    assert_eq!(
+        client.try_execute_batch(&payroll_id),
+        Err(Ok(SaveXError::TransferNotPending))
+    );
+    assert_eq!(
+        client.try_cancel_batch(&payer, &payroll_id),
+        Err(Ok(SaveXError::TransferNotPending))
+    );
+
+    // Cancelling the split refunds its scheduled leg
+    assert_eq!(client.cancel_batch(&payer, &split_batch_id), 1);
+    assert_eq!(client.get_transfer(&scheduled_id).status, TransferStatus::Cancelled);
+    let split_batch = client.get_batch(&split_batch_id);
+    assert_eq!(split_batch.status, BatchStatus::Cancelled);
+    assert_eq!(split_batch.pending_legs, 0);
+}
+
+#[test]
+fn test_stream_transfer() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let employer = Address::generate(&env);
+    let contractor = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&employer, &100_000);
+
+    env.ledger().set_timestamp(1000);
+
+    assert_eq!(
+        client.try_transfer_stream(&employer, &contractor, &token.address, &10_000, &2000, &2000),
+        Err(Ok(SaveXError::InvalidSchedule))
+    );
+    assert_eq!(
+        client.try_transfer_stream(&employer, &contractor, &token.address, &10_000, &999, &2000),
+        Err(Ok(SaveXError::InvalidTimestamp))
+    );
+
+    // 10_000 streams over 1000 seconds starting at 2000
+    let stream_id = client.transfer_stream(&employer, &contractor, &token.address, &10_000, &2000, &3000);
+    assert_eq!(client.get_transfer(&stream_id).status, TransferStatus::Locked);
+    assert_eq!(token.balance(&contract_id), 10_000);
+    assert_eq!(
+        client.try_withdraw_stream(&contractor, &stream_id),
+        Err(Ok(SaveXError::InsufficientBalance))
+    );
+
+    // Streams only settle through their own calls
+    env.ledger().set_timestamp(2000);
+    assert_eq!(
+        client.try_execute_scheduled_transfer(&stream_id),
+        Err(Ok(SaveXError::InvalidTimebound))
+    );
+    assert_eq!(
+        client.try_cancel_scheduled_transfer(&employer, &stream_id),
+        Err(Ok(SaveXError::InvalidTimebound))
+    );
+
+    env.ledger().set_timestamp(2250);
+    assert_eq!(client.get_stream_withdrawable(&stream_id), 2500);
+    assert_eq!(
+        client.try_withdraw_stream(&employer, &stream_id),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+    assert_eq!(client.withdraw_stream(&contractor, &stream_id), 2500);
+    assert_eq!(
+        contract_events(&env, &contract_id),
+        vec![
+            &env,
+            event_entry(&env, &events::StreamWithdrawn {
+                id: stream_id,
+                to: contractor.clone(),
+                amount: 2500,
+                withdrawn: 2500,
+            }),
+        ]
+    );
+
+    env.ledger().set_timestamp(3500);
+    assert_eq!(client.withdraw_stream(&contractor, &stream_id), 7500);
+    assert_eq!(token.balance(&contractor), 10_000);
+    assert_eq!(client.get_transfer(&stream_id).status, TransferStatus::Completed);
+    assert_eq!(client.get_stream(&stream_id).withdrawn, 10_000);
+    assert_eq!(client.get_stream_withdrawable(&stream_id), 0);
+
+    // Cancelling pays out the vested part and refunds the rest
+    let stream_id = client.transfer_stream(&employer, &contractor, &token.address, &4000, &4000, &8000);
+    env.ledger().set_timestamp(5000);
+    client.withdraw_stream(&contractor, &stream_id);
+    env.ledger().set_timestamp(6000);
+    client.cancel_stream(&employer, &stream_id);
+    assert_eq!(token.balance(&contractor), 10_000 + 2000);
+    assert_eq!(token.balance(&employer), 100_000 - 10_000 - 2000);
+    assert_eq!(token.balance(&contract_id), 0);
+    assert_eq!(client.get_transfer(&stream_id).status, TransferStatus::Cancelled);
+    assert_eq!(client.get_pending_transfers(&contractor, &0, &50).len(), 0);
+    assert_eq!(
+        client.try_withdraw_stream(&contractor, &stream_id),
+        Err(Ok(SaveXError::TransferNotPending))
+    );
+}
+
+#[test]
+fn test_split_tranches() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let sender = Address::generate(&env);
+    let recipient = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
+    let treasury = setup_fees(&env, &client, &admin);
+    token_admin.mint(&sender, &100_000);
+
+    env.ledger().set_timestamp(1000);
+
+    let tranche = |kind: TrancheKind, value: i128, execute_after: u64| Tranche { kind, value, execute_after };
+
+    assert_eq!(
+        client.try_transfer_split_tranches(
+            &sender,
+            &recipient,
+            &token.address,
+            &1000,
+            &vec![&env, tranche(TrancheKind::Amount, 300, 0), tranche(TrancheKind::Percentage, 50, 2000)],
+        ),
+        Err(Ok(SaveXError::InvalidAmount))
+    );
+    assert_eq!(
+        client.try_transfer_split_tranches(
+            &sender,
+            &recipient,
+            &token.address,
+            &1000,
+            &vec![&env, tranche(TrancheKind::Percentage, 101, 0)],
+        ),
+        Err(Ok(SaveXError::InvalidPercentage))
+    );
+    assert_eq!(
+        client.try_transfer_split_tranches(
+            &sender,
+            &recipient,
+            &token.address,
+            &1000,
+            &vec![&env, tranche(TrancheKind::Percentage, 100, 500)],
+        ),
+        Err(Ok(SaveXError::InvalidTimestamp))
+    );
+
+    // 1001 split 33/33/34: rounding dust and fee remainder land in the last tranche
+    let batch_id = client.transfer_split_tranches(
+        &sender,
+        &recipient,
+        &token.address,
+        &1001,
+        &vec![
+            &env,
+            tranche(TrancheKind::Percentage, 33, 0),
+            tranche(TrancheKind::Percentage, 33, 2000),
+            tranche(TrancheKind::Percentage, 34, 3000),
+        ],
+    );
+    let transfers = client.get_batch_transfers(&batch_id);
+    assert_eq!(transfers.len(), 3);
+    assert_eq!(transfers.get(0).unwrap().amount, 327);
+    assert_eq!(transfers.get(0).unwrap().status, TransferStatus::Completed);
+    assert_eq!(transfers.get(1).unwrap().amount, 327);
+    assert_eq!(transfers.get(1).unwrap().status, TransferStatus::Locked);
+    assert_eq!(transfers.get(2).unwrap().amount, 337);
+    assert_eq!(client.get_fee_receipt(&transfers.get(2).unwrap().id).gross_amount, 341);
+    assert_eq!(token.balance(&treasury), 10);
+    assert_eq!(token.balance(&recipient), 327);
+    assert_eq!(token.balance(&contract_id), 664);
+    assert_eq!(client.get_batch(&batch_id).totals.get(token.address.clone()), Some(1001));
+
+    env.ledger().set_timestamp(3000);
+    assert_eq!(client.execute_batch(&batch_id), 2);
+    assert_eq!(token.balance(&recipient), 991);
+    assert_eq!(client.get_batch(&batch_id).status, BatchStatus::Settled);
+
+    // Amounts and percentages can be mixed
+    let batch_id = client.transfer_split_tranches(
+        &sender,
+        &recipient,
+        &token.address,
+        &1000,
+        &vec![&env, tranche(TrancheKind::Amount, 250, 0), tranche(TrancheKind::Percentage, 75, 4000)],
+    );
+    let transfers = client.get_batch_transfers(&batch_id);
+    assert_eq!(transfers.get(0).unwrap().amount, 248);
+    assert_eq!(transfers.get(1).unwrap().amount, 742);
+    assert_eq!(client.cancel_batch(&sender, &batch_id), 1);
+}
+
+#[test]
+fn test_arbitrage_bot_configuration() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let executor = Address::generate(&env);
+    let outsider = Address::generate(&env);
+
+    client.initialize(&admin);
+    let (token_a, token_a_admin, token_b, router) = setup_swap_pair(&env, &client, &admin, 11_000_000);
+    token_a_admin.mint(&router.address, &1_000_000_000);
+    token_a_admin.mint(&executor, &10_000);
+
+    let cycle = vec![&env, token_a.address.clone(), token_b.address.clone(), token_a.address.clone()];
+
+    // An unconfigured bot refuses to trade
+    let unconfigured = ArbitrageBotClient::new(&env, &env.register(ArbitrageBot, ()));
+    assert_eq!(unconfigured.try_arb_get_config(), Err(Ok(SaveXError::NotInitialized)));
+    assert_eq!(
+        unconfigured.try_execute_triangular_arbitrage(&executor, &cycle, &1000, &0),
+        Err(Ok(SaveXError::NotInitialized))
+    );
+
+    let bot = setup_arbitrage_bot(&env, &client, &admin, &executor);
+    let config = bot.arb_get_config();
+    assert_eq!(config.router, router.address);
+    assert_eq!(config.factory, client.get_factory_address());
+    assert!(!config.is_paused);
+    assert!(bot.arb_is_executor(&executor));
+    assert_eq!(
+        bot.try_arb_initialize(&outsider, &router.address, &router.address),
+        Err(Ok(SaveXError::AlreadyInitialized))
+    );
+    assert_eq!(
+        bot.try_arb_set_executor(&outsider, &outsider, &true),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+    assert_eq!(
+        bot.try_execute_triangular_arbitrage(&outsider, &cycle, &1000, &0),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+
+    // Both contracts quote and trade against the same router
+    assert_eq!(client.get_soroswap_quote(&token_a.address, &token_b.address, &1000), 1100);
+    assert_eq!(bot.execute_triangular_arbitrage(&executor, &cycle, &1000, &200), 210);
+    assert_eq!(token_a.balance(&executor), 10_210);
+
+    bot.arb_pause(&admin);
+    assert_eq!(
+        bot.try_execute_triangular_arbitrage(&executor, &cycle, &1000, &0),
+        Err(Ok(SaveXError::ContractPaused))
+    );
+    bot.arb_unpause(&admin);
+
+    bot.arb_set_executor(&admin, &executor, &false);
+    assert!(!bot.arb_is_executor(&executor));
+    assert_eq!(
+        bot.try_execute_triangular_arbitrage(&executor, &cycle, &1000, &0),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+
+    let new_router = Address::generate(&env);
+    bot.arb_set_router(&admin, &new_router);
+    assert_eq!(
+        contract_events(&env, &bot.address),
+        vec![
+            &env,
+            event_entry(&env, &events::ArbitrageConfigUpdated {
+                admin: admin.clone(),
+                config: ArbitrageConfig {
+                    admin: admin.clone(),
+                    router: new_router.clone(),
+                    factory: client.get_factory_address(),
+                    flash_lender: None,
+                    is_paused: false,
+                },
+            }),
+        ]
+    );
+}
+
+#[test]
+fn test_round_trip_arbitrage() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let executor = Address::generate(&env);
+
+    client.initialize(&admin);
+    let (token_a, token_a_admin, token_b, cheap_router) = setup_swap_pair(&env, &client, &admin, 11_000_000);
+    token_a_admin.mint(&executor, &10_000);
+    let bot = setup_arbitrage_bot(&env, &client, &admin, &executor);
+
+    // Second venue pays 0.95 per unit
+    let dear_router = MockRouterClient::new(&env, &env.register(MockRouter, ()));
+    let dear_factory = MockFactoryClient::new(&env, &env.register(MockFactory, ()));
+    dear_factory.set_pair(&dear_router.address);
+    dear_router.set_rate(&9_500_000);
+    token_a_admin.mint(&dear_router.address, &1_000_000_000);
+
+    let route = ArbitrageRoute {
+        buy: ArbitrageVenue {
+            router: cheap_router.address.clone(),
+            factory: client.get_factory_address(),
+        },
+        sell: ArbitrageVenue {
+            router: dear_router.address.clone(),
+            factory: dear_factory.address.clone(),
+        },
+    };
+
+    assert_eq!(
+        bot.try_execute_arbitrage(&executor, &token_a.address, &token_a.address, &1000, &route, &0),
+        Err(Ok(SaveXError::InvalidPath))
+    );
+
+    // 1000 A buys 1100 B, which sells back for 1045 A
+    env.ledger().set_timestamp(1000);
+    let result = bot.execute_arbitrage(&executor, &token_a.address, &token_b.address, &1000, &route, &40);
+    assert_eq!(
+        result,
+        ArbitrageResult {
+            input_amount: 1000,
+            output_amount: 1045,
+            profit: 45,
+            path_used: vec![&env, token_a.address.clone(), token_b.address.clone(), token_a.address.clone()],
+            executed_at: 1000,
+        }
+    );
+    assert_eq!(token_a.balance(&executor), 10_045);
+    assert_eq!(token_b.balance(&executor), 0);
+    assert_eq!(token_a.balance(&bot.address), 0);
+    assert_eq!(token_b.balance(&bot.address), 0);
+
+    // Falling short of the minimum profit reverts the whole trade
+    assert!(bot
+        .try_execute_arbitrage(&executor, &token_a.address, &token_b.address, &1000, &route, &50)
+        .is_err());
+    assert_eq!(token_a.balance(&executor), 10_045);
+
+    // A venue overstating its output can't get the bot to pay out its own funds
+    token_a_admin.mint(&bot.address, &500);
+    dear_router.set_overstatement(&500);
+    let result = bot.execute_arbitrage(&executor, &token_a.address, &token_b.address, &1000, &route, &40);
+    assert_eq!(result.output_amount, 1045);
+    assert_eq!(token_a.balance(&executor), 10_090);
+    assert_eq!(token_a.balance(&bot.address), 500);
+}
+
+#[test]
+fn test_flash_arbitrage() {
+    let env = Env::default();
+    // The executor authorizes inside the lender's call, not at the root
+    env.mock_all_auths_allowing_non_root_auth();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let executor = Address::generate(&env);
+
+    client.initialize(&admin);
+    let (token_a, token_a_admin, token_b, router) = setup_swap_pair(&env, &client, &admin, 11_000_000);
+    token_a_admin.mint(&router.address, &1_000_000_000);
+    let bot = setup_arbitrage_bot(&env, &client, &admin, &executor);
+
+    // 0.09% fee on loans
+    let lender = MockFlashLenderClient::new(&env, &env.register(MockFlashLender, ()));
+    lender.set_fee_bps(&9);
+    token_a_admin.mint(&lender.address, &1_000_000);
+
+    let path = vec![&env, token_a.address.clone(), token_b.address.clone(), token_a.address.clone()];
+    let params = |path: &soroban_sdk::Vec<Address>, min_profit: i128| {
+        FlashArbitrageParams {
+            executor: executor.clone(),
+            path: path.clone(),
+            min_profit,
+        }
+        .to_xdr(&env)
+    };
+
+    // Callbacks are refused until the admin names a lender
+    assert_eq!(
+        bot.try_on_flash_loan(&token_a.address, &100_000, &90, &params(&path, 0)),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+    bot.arb_set_flash_lender(&admin, &lender.address);
+    assert_eq!(bot.arb_get_config().flash_lender, Some(lender.address.clone()));
+
+    // The bot only trades funds it was actually lent
+    assert_eq!(
+        bot.try_on_flash_loan(&token_a.address, &100_000, &90, &params(&path, 0)),
+        Err(Ok(SaveXError::InsufficientBalance))
+    );
+
+    // The cycle must start and end in the borrowed token
+    let open_path = vec![&env, token_a.address.clone(), token_b.address.clone()];
+    assert_eq!(
+        bot.try_on_flash_loan(&token_a.address, &100_000, &90, &params(&open_path, 0)),
+        Err(Ok(SaveXError::InvalidPath))
+    );
+
+    // 100_000 A -> 110_000 B -> 121_000 A; 100_090 goes back to the lender
+    lender.flash_loan(&bot.address, &token_a.address, &100_000, &params(&path, 20_000));
+    assert!(contract_events(&env, &bot.address).contains(event_entry(
+        &env,
+        &events::ArbitrageExecuted {
+            executor: executor.clone(),
+            path: path.clone(),
+            amount_in: 100_000,
+            amount_out: 121_000,
+            profit: 20_910,
+        }
+    )));
+    assert_eq!(token_a.balance(&lender.address), 1_000_090);
+    assert_eq!(token_a.balance(&bot.address), 20_910);
+    assert_eq!(token_b.balance(&bot.address), 0);
+    assert_eq!(bot.arb_get_retained(&token_a.address), 20_910);
+
+    // A cycle that can't clear the minimum profit reverts the loan
+    assert!(lender
+        .try_flash_loan(&bot.address, &token_a.address, &100_000, &params(&path, 30_000))
+        .is_err());
+    assert_eq!(token_a.balance(&bot.address), 20_910);
+    assert_eq!(token_a.balance(&lender.address), 1_000_090);
+
+    // Retained profit doesn't count as loan funds
+    assert_eq!(
+        bot.try_on_flash_loan(&token_a.address, &20_000, &0, &params(&path, 0)),
+        Err(Ok(SaveXError::InsufficientBalance))
+    );
+
+    // Only the admin can take the profit out
+    assert_eq!(
+        bot.try_arb_withdraw_profit(&executor, &token_a.address, &executor, &910),
+        Err(Ok(SaveXError::NotAuthorized))
+    );
+    assert_eq!(
+        bot.try_arb_withdraw_profit(&admin, &token_a.address, &admin, &20_911),
+        Err(Ok(SaveXError::InsufficientBalance))
+    );
+    bot.arb_withdraw_profit(&admin, &token_a.address, &admin, &20_000);
+    assert_eq!(
+        contract_events(&env, &bot.address),
+        vec![
+            &env,
+            event_entry(&env, &events::ArbitrageProfitWithdrawn {
+                admin: admin.clone(),
+                token: token_a.address.clone(),
+                to: admin.clone(),
+                amount: 20_000,
+            }),
+        ]
+    );
+    assert_eq!(token_a.balance(&admin), 20_000);
+    assert_eq!(bot.arb_get_retained(&token_a.address), 910);
+}
+
+#[test]
+fn test_cycle_simulation() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let executor = Address::generate(&env);
+
+    client.initialize(&admin);
+    let (token_a, _, token_b, _) = setup_swap_pair(&env, &client, &admin, 11_000_000);
+    let token_c = Address::generate(&env);
+    let bot = setup_arbitrage_bot(&env, &client, &admin, &executor);
+
+    let a = token_a.address.clone();
+    let b = token_b.address.clone();
+    let round_trip = vec![&env, a.clone(), b.clone(), a.clone()];
+    let triangle = vec![&env, a.clone(), b.clone(), token_c.clone(), a.clone()];
+    let open_path = vec![&env, a.clone(), b.clone()];
+
+    // Every hop pays 1.1, so profit is measured back in the starting token
+    assert_eq!(
+        bot.simulate_cycle(&round_trip, &1000),
+        CycleSimulation {
+            path: round_trip.clone(),
+            amount_in: 1000,
+            amount_out: 1210,
+            profit: 210,
+        }
+    );
+    assert_eq!(bot.estimate_arbitrage_profit(&a, &b, &1000), 210);
+    assert!(bot.has_arbitrage_opportunity(&a, &b, &1000, &2100));
+    assert!(!bot.has_arbitrage_opportunity(&a, &b, &1000, &2200));
+
+    assert_eq!(bot.try_simulate_cycle(&open_path, &1000), Err(Ok(SaveXError::InvalidPath)));
+    assert_eq!(bot.try_simulate_cycle(&round_trip, &0), Err(Ok(SaveXError::InvalidAmount)));
+
+    // The longer cycle wins; the open path is skipped
+    let best = bot.best_cycle(&vec![&env, round_trip.clone(), open_path.clone(), triangle.clone()], &1000);
+    assert_eq!(best.path, triangle);
+    assert_eq!(best.amount_out, 1331);
+    assert_eq!(best.profit, 331);
+
+    assert_eq!(
+        bot.try_best_cycle(&vec![&env, open_path.clone()], &1000),
+        Err(Ok(SaveXError::InvalidPath))
+    );
+
+    let mut too_many = soroban_sdk::Vec::new(&env);
+    for _ in 0..11 {
+        too_many.push_back(round_trip.clone());
+    }
+    assert_eq!(bot.try_best_cycle(&too_many, &1000), Err(Ok(SaveXError::BatchTooLarge)));
+}
+
+#[test]
+fn test_optimal_arbitrage_amount() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let admin = Address::generate(&env);
+    let token_a = Address::generate(&env);
+    let token_b = Address::generate(&env);
+    let token_c = Address::generate(&env);
+
+    let factory = MockFactoryClient::new(&env, &env.register(MockFactory, ()));
+    let pair_ab = MockPairClient::new(&env, &env.register(MockPair, ()));
+    let pair_bc = MockPairClient::new(&env, &env.register(MockPair, ()));
+    let pair_ca = MockPairClient::new(&env, &env.register(MockPair, ()));
+    factory.set_pair_for(&token_a, &token_b, &pair_ab.address);
+    factory.set_pair_for(&token_b, &token_c, &pair_bc.address);
+    factory.set_pair_for(&token_c, &token_a, &pair_ca.address);
+
+    let bot = ArbitrageBotClient::new(&env, &env.register(ArbitrageBot, ()));
+    let cycle = vec![&env, token_a.clone(), token_b.clone(), token_c.clone(), token_a.clone()];
+    assert_eq!(bot.try_optimal_arbitrage_amount(&cycle), Err(Ok(SaveXError::NotInitialized)));
+    bot.arb_initialize(&admin, &Address::generate(&env), &factory.address);
+
+    // C is worth 1.2 A in its pool with A but 1 A via B; token_0 is A in the
+    // last pool so the reserves are read the other way round
+    pair_ab.set_reserves(&token_a, &token_b, &1_000_000, &1_000_000);
+    pair_bc.set_reserves(&token_b, &token_c, &1_000_000, &1_000_000);
+    pair_ca.set_reserves(&token_a, &token_c, &1_200_000, &1_000_000);
+
+    assert_eq!(bot.optimal_arbitrage_amount(&cycle), 30_354);
+
+    // A wide mispricing can call for more than the first pool holds
+    pair_ca.set_reserves(&token_a, &token_c, &100_000_000, &1_000_000);
+    assert_eq!(bot.optimal_arbitrage_amount(&cycle), 3_002_988);
+    pair_ca.set_reserves(&token_a, &token_c, &1_200_000, &1_000_000);
+
+    // Going round the other way loses
+    let reverse = vec![&env, token_a.clone(), token_c.clone(), token_b.clone(), token_a.clone()];
+    assert_eq!(bot.optimal_arbitrage_amount(&reverse), 0);
+
+    assert_eq!(
+        bot.try_optimal_arbitrage_amount(&vec![&env, token_a.clone(), token_b.clone()]),
+        Err(Ok(SaveXError::InvalidPath))
+    );
+
+    // Reserves near the top of i128 don't overflow
+    let scale = 10_i128.pow(31);
+    pair_ab.set_reserves(&token_a, &token_b, &(1_000_000 * scale), &(1_000_000 * scale));
+    pair_bc.set_reserves(&token_b, &token_c, &(1_000_000 * scale), &(1_000_000 * scale));
+    pair_ca.set_reserves(&token_a, &token_c, &(1_200_000 * scale), &(1_000_000 * scale));
+    assert_eq!(bot.optimal_arbitrage_amount(&cycle), 303_548_688_849_754_805_926_593_050_102_341_679);
+
+    // An empty pool can't be traded through
+    pair_bc.set_reserves(&token_b, &token_c, &0, &0);
+    assert_eq!(bot.optimal_arbitrage_amount(&cycle), 0);
+}
+
+#[test]
+fn test_arbitrage_bot_cannot_take_over_savex() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let attacker = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    // Both contracts share one wasm, so the bot's functions run against
+    // SaveX's storage on a deployed instance
+    let result = env.as_contract(&contract_id, || {
+        ArbitrageBot::arb_initialize(env.clone(), attacker.clone(), attacker.clone(), attacker.clone())
+    });
+    assert_eq!(result, Err(SaveXError::AlreadyInitialized));
+    let result = env.as_contract(&contract_id, || ArbitrageBot::arb_get_config(env.clone()));
+    assert_eq!(result, Err(SaveXError::NotInitialized));
+
+    // And a configured bot can't be initialized as SaveX
+    let bot = ArbitrageBotClient::new(&env, &env.register(ArbitrageBot, ()));
+    bot.arb_initialize(&admin, &Address::generate(&env), &Address::generate(&env));
+    let result = env.as_contract(&bot.address, || SaveXContract::initialize(env.clone(), attacker.clone()));
+    assert_eq!(result, Err(SaveXError::AlreadyInitialized));
+}
+
+#[test]
+fn test_package_member_consent_and_lifecycle() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let owner1 = Address::generate(&env);
+    let owner2 = Address::generate(&env);
+    let member1 = Address::generate(&env);
+    let member2 = Address::generate(&env);
+
+    client.initialize(&admin);
+    setup_package_prices(&env, &client, &admin, 0);
+
+    env.ledger().set_timestamp(1000);
+    client.subscribe_package(&owner1, &PackageType::Business, &30);
+    client.subscribe_package(&owner2, &PackageType::Family, &10);
+
+    // The member has to agree to join
+    client.add_package_member(&owner1, &member1);
+    assert!(env.auths().iter().any(|(address, _)| *address == member1));
+    assert_eq!(
+        client.try_add_package_member(&owner2, &member1),
+        Err(Ok(SaveXError::MemberAlreadyAdded))
+    );
+
+    // and can leave on their own
+    client.leave_package(&member1);
+    assert!(contract_events(&env, &contract_id).contains(event_entry(
+        &env,
+        &events::PackageMemberRemoved { owner: owner1.clone(), member: member1.clone() }
+    )));
+    assert_eq!(client.get_package_members(&owner1).len(), 0);
+    assert_eq!(client.try_leave_package(&member1), Err(Ok(SaveXError::MemberNotFound)));
+
+    // Cancelling a package releases its members
+    client.add_package_member(&owner2, &member1);
+    client.cancel_package(&owner2);
+    assert_eq!(client.get_package_members(&owner2).len(), 0);
+    client.add_package_member(&owner1, &member1);
+    client.add_package_member(&owner1, &member2);
+
+    // So does letting it lapse: owner2 can take member2 once owner1 has expired
+    env.ledger().set_timestamp(1000 + 30 * 86400 + 1);
+    client.subscribe_package(&owner2, &PackageType::Family, &10);
+    client.add_package_member(&owner2, &member2);
+    assert_eq!(client.get_package_members(&owner1), vec![&env, member1.clone()]);
+    assert_eq!(client.get_package_members(&owner2), vec![&env, member2.clone()]);
+
+    // Re-subscribing on a smaller tier must still seat the kept members
+    client.set_package_tier(&admin, &PackageType::Family, &PackageTier {
+        discount_rate: 1500,
+        monthly_transfer_quota: 0,
+        monthly_volume_quota: 0,
+        discount_steps: vec![&env],
+        seat_limit: 0,
+    });
+    assert_eq!(
+        client.try_subscribe_package(&owner1, &PackageType::Family, &30),
+        Err(Ok(SaveXError::SeatLimitReached))
+    );
+    client.subscribe_package(&owner1, &PackageType::Business, &30);
+}
+
+#[test]
+fn test_batch_skips_settled_and_missing_legs() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let payer = Address::generate(&env);
+    let alice = Address::generate(&env);
+    let bob = Address::generate(&env);
+    let carol = Address::generate(&env);
+
+    client.initialize(&admin);
+
+    let (token, token_admin) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
+    token_admin.mint(&payer, &100_000);
+
+    env.ledger().set_timestamp(1000);
+
+    let entries = vec![
+        &env,
+        BatchEntry { recipient: alice.clone(), token: token.address.clone(), amount: 1000, execute_after: 2000 },
+        BatchEntry { recipient: bob.clone(), token: token.address.clone(), amount: 2000, execute_after: 2000 },
+        BatchEntry { recipient: carol.clone(), token: token.address.clone(), amount: 3000, execute_after: 2000 },
+    ];
+    let drop_leg = |id: u64| {
+        env.as_contract(&contract_id, || {
+            env.storage().persistent().remove(&PersistentDataKey::Transfer(id));
+        });
+    };
+
+    // One leg cancelled on its own, one gone from storage
+    let payroll_id = client.create_batch(&payer, &entries);
+    let legs = client.get_batch(&payroll_id).transfer_ids;
+    client.cancel_scheduled_transfer(&payer, &legs.get(0).unwrap());
+    drop_leg(legs.get(1).unwrap());
+
+    env.ledger().set_timestamp(2000);
+    assert_eq!(client.execute_batch(&payroll_id), 1);
+    assert_eq!(token.balance(&carol), 3000);
+    assert_eq!(token.balance(&alice), 0);
+
+    // Cancelling refunds what is still locked
+    env.ledger().set_timestamp(1000);
+    let refund_id = client.create_batch(&payer, &entries);
+    let legs = client.get_batch(&refund_id).transfer_ids;
+    client.cancel_scheduled_transfer(&payer, &legs.get(0).unwrap());
+    drop_leg(legs.get(2).unwrap());
+
+    let balance = token.balance(&payer);
+    assert_eq!(client.cancel_batch(&payer, &refund_id), 1);
+    assert_eq!(token.balance(&payer), balance + 2000);
+    assert_eq!(client.get_batch(&refund_id).status, BatchStatus::Cancelled);
+}
+
+#[test]
+fn test_package_end_date_boundary() {
+    let env = Env::default();
+    env.mock_all_auths();
+
+    let contract_id = env.register(SaveXContract, ());
+    let client = SaveXContractClient::new(&env, &contract_id);
+
+    let admin = Address::generate(&env);
+    let user = Address::generate(&env);
+
+    client.initialize(&admin);
+    setup_fees(&env, &client, &admin);
+    setup_package_prices(&env, &client, &admin, 0);
+
+    let (token, _) = create_token_contract(&env, &admin);
+    register_token(&env, &client, &admin, &token.address);
+
+    env.ledger().set_timestamp(1000);
+    client.subscribe_package(&user, &PackageType::Family, &30);
+    let end_date = client.get_package(&user).unwrap().end_date;
+
+    // Just before the end date the package still counts everywhere
+    env.ledger().set_timestamp(end_date - 1);
+    assert_eq!(client.calculate_fee(&user, &token.address, &10_000, &false, &1).total, 85);
+    assert_eq!(
+        client.try_subscribe_package(&user, &PackageType::Family, &30),
+        Err(Ok(SaveXError::PackageAlreadyActive))
+    );
+
+    // At the end date it has lapsed for fees and for subscribing alike
+    env.ledger().set_timestamp(end_date);
+    assert_eq!(client.calculate_fee(&user, &token.address, &10_000, &false, &1).total, 100);
+    client.subscribe_package(&user, &PackageType::Family, &30);
+    assert_eq!(client.get_package(&user).unwrap().start_date, end_date);
 }
@@ -1,6 +1,6 @@
 //! Data types and structures for SaveX contract
 
-use soroban_sdk::{contracttype, Address, String};
+use soroban_sdk::{contracterror, contracttype, Address, BytesN, Map, String};
 
 // ============================================================================
 // Storage Keys
@@ -13,10 +13,18 @@ pub enum DataKey {
     Admin,
     TransferCounter,
     RateLockCounter,
+    RecurringCounter,
+    BatchCounter,
     PackageCounter,
     IsPaused,
     RouterAddress,  // Soroswap Router contract address for swaps
     FactoryAddress, // Soroswap Factory contract address for pool queries
+    FeeStructure,   // Fee parameters charged on transfers
+    Treasury,       // Address that receives collected fees
+    PackagePrice(PackageType),
+    PackageTier(PackageType),
+    HedgeReserve(Address),      // Reserve backing rate locks, per output token
+    ReferenceRate(Address, Address), // Admin-set rate (7 decimals) locks are checked against
 }
 
 #[derive(Clone)]
@@ -25,6 +33,28 @@ pub enum PersistentDataKey {
     Transfer(u64),
     RateLock(u64),
     Package(Address),
+    PackageHistory(Address),
+    PackageMember(Address),     // Owner of the package a member belongs to
+    PackageMembers(Address),    // Members added by a package owner
+    FeeReceipt(u64),
+    Recurring(u64),
+    Escrow(u64),                // Release conditions of an escrow transfer
+    Batch(u64),
+    Stream(u64),                // Vesting schedule of a stream transfer
+    Token(Address),             // TokenInfo of a registered token
+    TokenFeeStructure(Address), // Fee parameters in the token's own units
+    IndexLength(UserIndex),
+    IndexEntry(UserIndex, u64), // Position in the index -> record ID
+}
+
+/// Per-user indexes of record IDs, in creation order
+#[derive(Clone)]
+#[contracttype]
+pub enum UserIndex {
+    Sent(Address),
+    Received(Address),
+    RateLocks(Address),
+    Pending(Address),           // Transfers that were created unsettled; filtered by status
 }
 
 // ============================================================================
@@ -56,6 +86,10 @@ pub enum TransferType {
     Scheduled,
     Split,
     Batched,
+    Claimable,  // Escrowed until someone presents the claim code
+    Recurring,  // One installment of a recurring transfer
+    Escrow,     // Released on approval, refundable after the deadline
+    Stream,     // Vests linearly between a start and an end timestamp
 }
 
 #[derive(Clone, Debug, PartialEq)]
@@ -65,6 +99,9 @@ pub enum TransferStatus {
     Locked,
     Completed,
     Cancelled,
+    Claimable,  // Waiting for the claim code, `to` is the sender until claimed
+    Claimed,
+    Disputed,   // Escrow frozen until the arbiter resolves it
 }
 
 #[derive(Clone, Debug)]
@@ -83,6 +120,124 @@ pub struct Transfer {
     pub created_at: u64,
     pub has_rate_lock: bool,
     pub rate_lock_id: u64,
+    pub claim_hash: BytesN<32>,     // SHA-256 of the claim code, zero unless claimable
+    pub batch_id: u64,              // 0 unless created by a batch or split
+}
+
+/// Who can release an escrow: the arbiter alone, or `threshold` of `approvers`
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct EscrowRelease {
+    pub arbiter: Address,           // Also resolves disputes
+    pub approvers: soroban_sdk::Vec<Address>,
+    pub threshold: u32,             // 0 when only the arbiter can release
+}
+
+/// Release conditions and approvals of an escrow transfer, keyed by its transfer ID
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct EscrowTerms {
+    pub transfer_id: u64,
+    pub release: EscrowRelease,
+    pub approvals: soroban_sdk::Vec<Address>,
+}
+
+/// Vesting schedule of a stream transfer, keyed by its transfer ID
+/// `Transfer::amount` vests linearly from `start` to `end`
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct StreamSchedule {
+    pub transfer_id: u64,
+    pub start: u64,
+    pub end: u64,
+    pub withdrawn: i128,
+}
+
+// ============================================================================
+// Split Tranches
+// ============================================================================
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub enum TrancheKind {
+    Percentage, // `value` is 0-100 of the total
+    Amount,     // `value` is a gross token amount
+}
+
+/// One leg of a split; `execute_after` of 0 sends it immediately
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Tranche {
+    pub kind: TrancheKind,
+    pub value: i128,
+    pub execute_after: u64,
+}
+
+// ============================================================================
+// Batches
+// ============================================================================
+
+/// One line of a batch; `execute_after` of 0 sends it immediately
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct BatchEntry {
+    pub recipient: Address,
+    pub token: Address,
+    pub amount: i128,
+    pub execute_after: u64,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub enum BatchStatus {
+    Pending,    // Some legs are still locked
+    Settled,    // Every leg was executed or cancelled
+    Cancelled,
+}
+
+/// Groups the transfer records created by one batch or split call
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct Batch {
+    pub id: u64,
+    pub creator: Address,
+    pub transfer_ids: soroban_sdk::Vec<u64>,
+    pub totals: Map<Address, i128>, // Gross amount per token, fees included
+    pub pending_legs: u32,
+    pub status: BatchStatus,
+    pub created_at: u64,
+}
+
+// ============================================================================
+// Recurring Transfers
+// ============================================================================
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub enum RecurringStatus {
+    Active,
+    Paused,
+    Cancelled,
+    Completed,
+}
+
+/// A standing order paying `amount` every `interval` from a pre-funded escrow
+#[derive(Clone, Debug)]
+#[contracttype]
+pub struct RecurringTransfer {
+    pub id: u64,
+    pub from: Address,
+    pub to: Address,
+    pub token: Address,
+    pub amount: i128,               // Gross amount per installment, fees come out of it
+    pub interval: u64,
+    pub occurrences: u32,
+    pub executed_count: u32,
+    pub next_execution: u64,
+    pub status: RecurringStatus,
+    pub paused_at: u64,             // 0 unless paused
+    pub created_at: u64,
+    pub transfers: soroban_sdk::Vec<u64>, // Transfer ID of each executed installment
 }
 
 // ============================================================================
@@ -101,6 +256,9 @@ pub struct RateLock {
     pub expiry: u64,
     pub is_active: bool,
     pub created_at: u64,
+    pub remaining_amount: i128, // Escrowed part of `amount` not yet used by transfers
+    pub premium: i128,          // Paid in `from_token` on top of the escrow
+    pub fills: soroban_sdk::Vec<u64>,   // Transfer IDs that used the lock, oldest first
 }
 
 // ============================================================================
@@ -126,13 +284,69 @@ pub struct Package {
     pub start_date: u64,
     pub end_date: u64,
     pub is_active: bool,
+    pub payment_token: Address,
+    pub price_per_day: i128,
+    pub prepaid: i128,             // Payment still held by the contract
+    pub period_start: u64,         // Start of the current monthly usage period
+    pub period_transfer_count: u32,
+    pub period_volume: i128,
+}
+
+/// Extra discount once `min_transfers` have been made in the period
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct DiscountStep {
+    pub min_transfers: u32,
+    pub extra_discount: u32,       // In basis points, added to the tier discount
+}
+
+/// Admin-configured benefits of a package type
+/// Quotas are per monthly period; 0 means unlimited
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct PackageTier {
+    pub discount_rate: u32,        // In basis points
+    pub monthly_transfer_quota: u32,
+    pub monthly_volume_quota: i128,
+    pub discount_steps: soroban_sdk::Vec<DiscountStep>,
+    pub seat_limit: u32,           // Members the owner can add besides themselves
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub enum PackageAction {
+    Subscribed,
+    Renewed,
+    Upgraded,
+    Downgraded,
+    Cancelled,
+}
+
+/// One step of an owner's subscription timeline
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct PackageHistoryEntry {
+    pub action: PackageAction,
+    pub package_type: PackageType, // Package type after the action
+    pub timestamp: u64,
+    pub end_date: u64,
+    pub amount_paid: i128,
+    pub refund: i128,
+}
+
+/// Subscription price of a package type, set by the admin
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct PackagePrice {
+    pub token: Address,
+    pub price_per_day: i128,       // 0 for a free package
 }
 
 // ============================================================================
 // Fee Management
 // ============================================================================
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq)]
 #[contracttype]
 pub struct FeeStructure {
     pub base_fee: i128,            // Base fee in stroops
@@ -150,11 +364,25 @@ pub struct FeeBreakdown {
     pub total: i128,
 }
 
+/// Fee actually charged on a transfer, stored per transfer ID
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct FeeReceipt {
+    pub transfer_id: u64,
+    pub payer: Address,
+    pub token: Address,            // Token the fee was paid in
+    pub gross_amount: i128,        // Amount before the fee
+    pub fee: i128,                 // Sent to the treasury (0 if no treasury is set)
+    pub net_amount: i128,          // Amount left for the transfer
+    pub charged_at: u64,
+}
+
 // ============================================================================
 // Token Information
 // ============================================================================
 
-#[derive(Clone, Debug)]
+/// Registry entry for a token accepted by transfers
+#[derive(Clone, Debug, PartialEq)]
 #[contracttype]
 pub struct TokenInfo {
     pub address: Address,
@@ -184,11 +412,85 @@ pub struct SwapResult {
     pub executed_at: u64,
 }
 
+// ============================================================================
+// Arbitrage Bot
+// ============================================================================
+
+/// Storage keys of the `ArbitrageBot` contract, separate from SaveX's own
+#[derive(Clone)]
+#[contracttype]
+pub enum ArbitrageDataKey {
+    Config,
+    Executor(Address),          // Addresses allowed to run trades
+    Retained(Address),          // Flash-loan profit the bot holds, per token
+}
+
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct ArbitrageConfig {
+    pub admin: Address,
+    pub router: Address,
+    pub factory: Address,       // Used to find the pair each swap pays into
+    pub flash_lender: Option<Address>, // Only lender whose callbacks are accepted
+    pub is_paused: bool,
+}
+
+/// A DEX to trade on: its router and the factory that knows its pairs
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct ArbitrageVenue {
+    pub router: Address,
+    pub factory: Address,
+}
+
+/// The two legs of a round trip: buy on one venue, sell back on the other
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct ArbitrageRoute {
+    pub buy: ArbitrageVenue,
+    pub sell: ArbitrageVenue,
+}
+
+/// Flash loan `data` for the bot: who trades, the cycle to run and the profit it must clear
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct FlashArbitrageParams {
+    pub executor: Address,
+    pub path: soroban_sdk::Vec<Address>,
+    pub min_profit: i128,
+}
+
+/// Quoted outcome of running `amount_in` round a cycle, net of router fees
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct CycleSimulation {
+    pub path: soroban_sdk::Vec<Address>,
+    pub amount_in: i128,
+    pub amount_out: i128,
+    pub profit: i128,           // amount_out - amount_in; negative when the cycle loses
+}
+
+/// Receipt of an arbitrage trade; amounts and profit are in the starting token
+#[derive(Clone, Debug, PartialEq)]
+#[contracttype]
+pub struct ArbitrageResult {
+    pub input_amount: i128,
+    pub output_amount: i128,
+    pub profit: i128,
+    pub path_used: soroban_sdk::Vec<Address>,
+    pub executed_at: u64,
+}
+
 // ============================================================================
 // Error Codes
 // ============================================================================
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+// Soroban caps error enums at 50 cases, so the generic transfer codes
+// (`TransferNotFound`, `TransferNotPending`, `TimeBoundNotMet`) are shared
+// by recurring transfers, escrows, batches, streams and other transfer-like records
+
+#[contracterror]
+#[derive(Clone, Copy, Debug, Eq, PartialEq, PartialOrd, Ord)]
 #[repr(u32)]
 pub enum SaveXError {
     NotAuthorized = 1,
@@ -207,4 +509,38 @@ pub enum SaveXError {
     InvalidTimebound = 14,
     TransferNotPending = 15,
     RouterNotConfigured = 16,
+    FactoryNotConfigured = 17,
+    BatchTooLarge = 18,          // More than 50 recipients
+    EmptyBatch = 19,
+    LengthMismatch = 20,         // Recipients and amounts differ in length
+    InvalidPercentage = 21,
+    InvalidRate = 22,
+    DurationTooLong = 23,        // Rate lock longer than 24h
+    InvalidDuration = 24,        // Package duration outside 1-365 days
+    RateLockNotActive = 25,
+    AmountExceedsLock = 26,
+    TimeBoundNotMet = 27,
+    PackageNotFound = 28,
+    PackageAlreadyActive = 29,
+    InvalidPath = 30,
+    ProfitTooLow = 31,
+    FeeExceedsAmount = 32,
+    InvalidFeeStructure = 33,
+    TreasuryNotConfigured = 34,
+    FeeReceiptNotFound = 35,
+    TokenNotRegistered = 36,
+    InvalidDecimals = 37,        // More than 18 decimals
+    PackagePriceNotSet = 38,
+    InvalidPackageChange = 39,   // Upgrade to a lower tier or downgrade to a higher one
+    InvalidPackageTier = 40,
+    SeatLimitReached = 41,
+    MemberAlreadyAdded = 42,     // Address already belongs to a package
+    MemberNotFound = 43,
+    InsufficientHedgeReserve = 44,
+    RateLockNotExpired = 45,
+    InvalidClaimCode = 46,
+    ClaimExpired = 47,
+    ClaimNotExpired = 48,
+    InvalidSchedule = 49,        // Zero interval, occurrences outside 1-120 or stream end before start
+    InvalidApprovers = 50,       // Threshold outside 1-N or duplicate approvers
 }